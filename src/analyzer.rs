@@ -0,0 +1,127 @@
+use crate::compound::Decompounder;
+use crate::filter::{
+    Lowercase, NGramFilter, ShingleFilter, Stem, StopwordFilter, SynonymFilter, TokenFilter,
+};
+use crate::lexer::{Language, Lexer, Normalization, Token};
+use serde::{Deserialize, Serialize};
+
+/// Turns a document or query into the tokens that are indexed or searched.
+///
+/// Implement this to experiment with analysis without touching `Model`; the
+/// same analyzer is used for indexing and querying, so both stay consistent.
+pub trait Analyzer {
+    /// Analyze `text` written in `language` into tokens.
+    fn analyze<'a>(&self, text: &'a str, language: Language) -> Vec<Token<'a>>;
+
+    /// Analyze `text` and keep only the terms.
+    fn terms(&self, text: &str, language: Language) -> Vec<String> {
+        self.analyze(text, language)
+            .into_iter()
+            .map(|token| token.term)
+            .collect()
+    }
+}
+
+/// A filter stage of a [`StandardAnalyzer`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Filter {
+    Lowercase,
+    Normalize(Normalization),
+    Stopwords(StopwordFilter),
+    Stem,
+    Decompound(Decompounder),
+    Synonyms(SynonymFilter),
+    NGrams(NGramFilter),
+    Shingles(ShingleFilter),
+}
+
+impl TokenFilter for Filter {
+    fn filter<'a>(
+        &self,
+        tokens: Vec<Token<'a>>,
+        input: &'a str,
+        language: Language,
+    ) -> Vec<Token<'a>> {
+        match self {
+            Filter::Lowercase => Lowercase.filter(tokens, input, language),
+            Filter::Normalize(normalization) => normalization.filter(tokens, input, language),
+            Filter::Stopwords(stopwords) => stopwords.filter(tokens, input, language),
+            Filter::Stem => Stem.filter(tokens, input, language),
+            Filter::Decompound(decompounder) => decompounder.filter(tokens, input, language),
+            Filter::Synonyms(synonyms) => synonyms.filter(tokens, input, language),
+            Filter::NGrams(ngrams) => ngrams.filter(tokens, input, language),
+            Filter::Shingles(shingles) => shingles.filter(tokens, input, language),
+        }
+    }
+}
+
+/// The lexer as tokenizer followed by an ordered chain of filters.
+///
+/// The default chain lowercases, normalizes and stems words, adds bigram and
+/// trigram shingles, then drops stopwords and punctuation.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StandardAnalyzer {
+    /// Lex equations and variables as case-preserving formula terms, which
+    /// never collide with the lowercased word terms.
+    #[serde(default)]
+    pub formulas: bool,
+    pub filters: Vec<Filter>,
+}
+
+impl Default for StandardAnalyzer {
+    fn default() -> Self {
+        Self {
+            formulas: false,
+            filters: vec![
+                Filter::Lowercase,
+                Filter::Normalize(Normalization::default()),
+                Filter::Stem,
+                Filter::Shingles(ShingleFilter::default()),
+                Filter::Stopwords(StopwordFilter::default()),
+            ],
+        }
+    }
+}
+
+impl StandardAnalyzer {
+    /// Use `stopwords` for the stopword stage and for the shingle edges, so
+    /// both agree on what a stopword is. Adds a stopword stage if there is none.
+    pub fn set_stopwords(&mut self, stopwords: StopwordFilter) {
+        let mut found = false;
+        for filter in &mut self.filters {
+            match filter {
+                Filter::Stopwords(stage) => {
+                    *stage = stopwords.clone();
+                    found = true;
+                }
+                Filter::Shingles(shingles) => shingles.stopwords = stopwords.clone(),
+                _ => {}
+            }
+        }
+        if !found {
+            self.filters.push(Filter::Stopwords(stopwords));
+        }
+    }
+
+    /// Insert `filter` after the first stage matching `after`, or append it.
+    pub fn insert_after(&mut self, after: fn(&Filter) -> bool, filter: Filter) {
+        match self.filters.iter().position(after) {
+            Some(idx) => self.filters.insert(idx + 1, filter),
+            None => self.filters.push(filter),
+        }
+    }
+}
+
+impl Analyzer for StandardAnalyzer {
+    fn analyze<'a>(&self, text: &'a str, language: Language) -> Vec<Token<'a>> {
+        let tokens = Lexer::with_language(text, language)
+            .with_formulas(self.formulas)
+            .raw()
+            .tokens()
+            .collect();
+
+        self.filters.iter().fold(tokens, |tokens, filter| {
+            filter.filter(tokens, text, language)
+        })
+    }
+}
@@ -0,0 +1,131 @@
+use crate::lexer::Normalization;
+use std::collections::HashMap;
+
+/// An indexed term offered as completion of a typed prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub term: String,
+    /// Most frequent spelling of the term starting with the prefix.
+    pub surface: String,
+    pub df: usize,
+}
+
+/// A spelling of a term under its normalized lookup key.
+#[derive(Debug, Clone)]
+struct Key {
+    key: String,
+    surface: String,
+    count: usize,
+    /// Index into `Completer::terms`.
+    term: usize,
+}
+
+/// Prefix lookup over the surface forms of an index vocabulary.
+///
+/// Keys are kept in one sorted vector, so a lookup is a binary search for
+/// the start of the prefix range. Build it once with `Model::completer` and
+/// reuse it while the user types.
+#[derive(Debug, Clone, Default)]
+pub struct Completer {
+    keys: Vec<Key>,
+    terms: Vec<(String, usize)>,
+}
+
+/// Lookup key of a typed or indexed word: lowercased and normalized like terms,
+/// but not stemmed, since a prefix is rarely a complete word.
+fn normalize(word: &str) -> String {
+    Normalization::default().word(&word.to_lowercase())
+}
+
+impl Completer {
+    /// Creates a completer from `(term, df, spellings with counts)`. A term
+    /// without recorded spellings is looked up by the term itself.
+    pub fn new<'a, I>(vocabulary: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, usize, Vec<(&'a str, usize)>)>,
+    {
+        let mut completer = Self::default();
+        for (term, df, mut surfaces) in vocabulary {
+            if surfaces.is_empty() {
+                surfaces.push((term, df));
+            }
+            let index = completer.terms.len();
+            completer.terms.push((term.to_string(), df));
+            completer
+                .keys
+                .extend(surfaces.into_iter().map(|(surface, count)| Key {
+                    key: normalize(surface),
+                    surface: surface.to_string(),
+                    count,
+                    term: index,
+                }));
+        }
+        completer.keys.sort_by(|a, b| a.key.cmp(&b.key));
+        completer
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Up to `n` terms with a spelling starting with `prefix`, most frequent
+    /// in the index first.
+    pub fn complete(&self, prefix: &str, n: usize) -> Vec<Completion> {
+        let prefix = normalize(prefix);
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        let start = self.keys.partition_point(|key| key.key < prefix);
+        let matches = self.keys[start..]
+            .iter()
+            .take_while(|key| key.key.starts_with(&prefix));
+
+        // The most frequent matching spelling of each term
+        let mut best: HashMap<usize, &Key> = HashMap::new();
+        for key in matches {
+            best.entry(key.term)
+                .and_modify(|current| {
+                    if (key.count, &current.surface) > (current.count, &key.surface) {
+                        *current = key;
+                    }
+                })
+                .or_insert(key);
+        }
+
+        let mut completions: Vec<Completion> = best
+            .into_iter()
+            .map(|(index, key)| {
+                let (term, df) = &self.terms[index];
+                Completion {
+                    term: term.clone(),
+                    surface: key.surface.clone(),
+                    df: *df,
+                }
+            })
+            .collect();
+        completions.sort_by(|a, b| b.df.cmp(&a.df).then_with(|| a.surface.cmp(&b.surface)));
+        completions.truncate(n);
+        completions
+    }
+
+    /// Up to `n` completions of the last word of a partially typed query, each
+    /// the whole query with that word completed.
+    pub fn complete_query(&self, input: &str, n: usize) -> Vec<String> {
+        let start = input
+            .rfind(|c: char| c.is_whitespace() || "\"()+-:".contains(c))
+            .map_or(0, |i| {
+                i + input[i..].chars().next().map_or(1, char::len_utf8)
+            });
+        let (head, word) = input.split_at(start);
+
+        self.complete(word, n)
+            .into_iter()
+            .map(|completion| format!("{}{}", head, completion.surface))
+            .collect()
+    }
+}
@@ -0,0 +1,224 @@
+use crate::filter::TokenFilter;
+use crate::lexer::{Language, Normalization, Token, TokenKind};
+use anyhow::{Context, Result};
+use rust_stemmers::Stemmer;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs, path::Path};
+
+/// Linking elements (Fugenelemente) that may join German compound parts.
+static LINKING_ELEMENTS: &[&str] = &["s", "es", "n", "en", "e"];
+
+/// Splits compound words into known parts.
+///
+/// The dictionary holds stems, so it can be built straight from the terms of
+/// an index or from a plain word list stemmed in the document language. As a
+/// filter it splits the surface text and emits stemmed part terms, so it
+/// belongs after [`crate::filter::Stem`] and, to keep parts out of shingles,
+/// after [`crate::filter::ShingleFilter`] in an analysis chain.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Decompounder {
+    dictionary: HashSet<String>,
+    /// Minimum length of a part in characters, linking element excluded.
+    pub min_part_len: usize,
+    /// Normalization applied to parts before stemming, matching the index.
+    #[serde(default)]
+    pub normalization: Normalization,
+}
+
+impl Default for Decompounder {
+    fn default() -> Self {
+        Self {
+            dictionary: HashSet::new(),
+            min_part_len: 4,
+            normalization: Normalization::default(),
+        }
+    }
+}
+
+impl Decompounder {
+    /// Creates a decompounder from already stemmed terms, such as the keys of
+    /// `Model::df`. Terms that are not purely alphabetic are skipped.
+    pub fn from_terms<I, S>(terms: I, min_part_len: usize) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let dictionary = terms
+            .into_iter()
+            .map(Into::into)
+            .filter(|term: &String| {
+                term.chars().count() >= min_part_len && term.chars().all(char::is_alphabetic)
+            })
+            .collect();
+
+        Self {
+            dictionary,
+            min_part_len,
+            normalization: Normalization::default(),
+        }
+    }
+
+    /// Adds the words of a file with one word per line, normalized and stemmed
+    /// the way the lexer would for `language`.
+    pub fn load_word_list<P: AsRef<Path>>(&mut self, path: P, language: Language) -> Result<()> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("could not read word list {:?}", path))?;
+        let stemmer = Stemmer::create(language.into());
+
+        for word in data.lines().map(str::trim) {
+            if word.chars().count() >= self.min_part_len {
+                let word = self.normalization.word(&word.to_lowercase());
+                self.dictionary.insert(stemmer.stem(&word).to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of known part stems.
+    pub fn len(&self) -> usize {
+        self.dictionary.len()
+    }
+
+    /// Whether the dictionary is empty.
+    pub fn is_empty(&self) -> bool {
+        self.dictionary.is_empty()
+    }
+
+    /// Byte length of the known word at the start of `chunk`: the chunk without
+    /// a trailing linking element if `linking` is allowed and that is known,
+    /// else the whole chunk.
+    fn known_len(&self, chunk: &str, stemmer: &Stemmer, linking: bool) -> Option<usize> {
+        let lowered = chunk.to_lowercase();
+        let known = |word: &str| {
+            word.chars().count() >= self.min_part_len
+                && self
+                    .dictionary
+                    .contains(stemmer.stem(&self.normalization.word(word)).as_ref())
+        };
+
+        // A linking element is split off even if the stemmer would drop it
+        // anyway, so that part spans never include it
+        let linked = linking
+            .then(|| {
+                LINKING_ELEMENTS.iter().find_map(|link| {
+                    lowered
+                        .strip_suffix(link)
+                        .filter(|stripped| known(stripped))
+                        .map(|_| chunk.len() - link.len())
+                })
+            })
+            .flatten();
+        linked.or_else(|| known(&lowered).then_some(chunk.len()))
+    }
+
+    /// Splits `word` into the byte ranges of at least two known parts, linking
+    /// elements excluded, preferring the segmentation with the fewest parts.
+    /// Returns `None` if the word is not a compound of known parts.
+    pub fn split(&self, word: &str, stemmer: &Stemmer) -> Option<Vec<(usize, usize)>> {
+        if word.chars().count() < 2 * self.min_part_len {
+            return None;
+        }
+
+        let bounds: Vec<usize> = word
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(std::iter::once(word.len()))
+            .collect();
+        let last = bounds.len() - 1;
+
+        // best[i]: (fewest parts covering word[bounds[i]..], end index of the
+        // first chunk, byte length of the known word in that chunk)
+        let mut best: Vec<Option<(usize, usize, usize)>> = vec![None; bounds.len()];
+        best[last] = Some((0, last, 0));
+
+        for i in (0..last).rev() {
+            for j in i + 1..=last {
+                // The whole word is no segmentation, even if it is known itself
+                if i == 0 && j == last {
+                    continue;
+                }
+                let Some((parts, _, _)) = best[j] else {
+                    continue;
+                };
+                let chunk = &word[bounds[i]..bounds[j]];
+                let Some(len) = self.known_len(chunk, stemmer, j != last) else {
+                    continue;
+                };
+                if best[i].is_none_or(|(fewest, _, _)| parts + 1 < fewest) {
+                    best[i] = Some((parts + 1, j, len));
+                }
+            }
+        }
+
+        let (parts, _, _) = best[0]?;
+        if parts < 2 {
+            return None;
+        }
+
+        let mut ranges = Vec::with_capacity(parts);
+        let mut i = 0;
+        while i < last {
+            let (_, next, len) = best[i]?;
+            ranges.push((bounds[i], bounds[i] + len));
+            i = next;
+        }
+        Some(ranges)
+    }
+}
+
+/// Emits every word token followed by tokens for its compound parts, whose
+/// terms are normalized and stemmed like the lexer's.
+impl TokenFilter for Decompounder {
+    fn filter<'a>(
+        &self,
+        tokens: Vec<Token<'a>>,
+        _input: &'a str,
+        language: Language,
+    ) -> Vec<Token<'a>> {
+        let stemmer = Stemmer::create(language.into());
+        let mut expanded = Vec::with_capacity(tokens.len());
+
+        for token in tokens {
+            let parts = match token.kind {
+                TokenKind::Word => self.split(token.text, &stemmer),
+                _ => None,
+            };
+            let offset = token.span.start;
+            let text = token.text;
+            expanded.push(token);
+
+            for (start, end) in parts.into_iter().flatten() {
+                let part = &text[start..end];
+                expanded.push(Token {
+                    kind: TokenKind::Word,
+                    text: part,
+                    term: stemmer
+                        .stem(&self.normalization.word(&part.to_lowercase()))
+                        .to_string(),
+                    span: offset + start..offset + end,
+                });
+            }
+        }
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_compound_that_is_known_itself() {
+        let decompounder = Decompounder::from_terms(["ladung", "transport", "ladungstransport"], 4);
+        let stemmer = Stemmer::create(Language::German.into());
+        let word = "Ladungstransport";
+
+        let parts = decompounder.split(word, &stemmer).unwrap();
+        let parts: Vec<&str> = parts
+            .iter()
+            .map(|&(start, end)| &word[start..end])
+            .collect();
+        assert_eq!(parts, ["Ladung", "transport"]);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::question::QuestionId;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+/// Free-form fields stored with a document and returned with search hits.
+pub type Metadata = BTreeMap<String, String>;
+
+/// Metadata keys set by the indexer.
+pub mod meta {
+    /// File the document was read from.
+    pub const PATH: &str = "path";
+    /// Id of the Neos tree node of the page.
+    pub const NODE: &str = "node";
+    /// Ids of the tree nodes from the root down to the page, joined by ` > `.
+    pub const BREADCRUMB: &str = "breadcrumb";
+    /// Section number of a question.
+    pub const SECTION: &str = "section";
+    pub const TITLE: &str = "title";
+    /// Current tags of a question, comma separated as in `tags.csv`.
+    pub const TAGS: &str = "tags";
+}
+
+/// Identifies a document of the index.
+///
+/// Serialized as a string: a file is its path, so indexes keyed by path stay
+/// readable, a tree node is `node:<id>` and a question `question:<id>#<section>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum DocId {
+    File(PathBuf),
+    /// A whole content page, by tree node id.
+    Node(String),
+    /// A single question of a content page.
+    Question(QuestionId),
+}
+
+impl fmt::Display for DocId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocId::File(path) => write!(f, "{}", path.display()),
+            DocId::Node(id) => write!(f, "node:{}", id),
+            DocId::Question(question) => {
+                write!(f, "question:{}#{}", question.node_id, question.section)
+            }
+        }
+    }
+}
+
+impl From<String> for DocId {
+    fn from(id: String) -> Self {
+        if let Some(node_id) = id.strip_prefix("node:") {
+            return DocId::Node(node_id.to_string());
+        }
+        if let Some((node_id, section)) = id
+            .strip_prefix("question:")
+            .and_then(|question| question.rsplit_once('#'))
+        {
+            return DocId::Question(QuestionId::new(node_id, section));
+        }
+        DocId::File(PathBuf::from(id))
+    }
+}
+
+impl From<DocId> for String {
+    fn from(id: DocId) -> Self {
+        id.to_string()
+    }
+}
+
+impl From<PathBuf> for DocId {
+    fn from(path: PathBuf) -> Self {
+        DocId::File(path)
+    }
+}
+
+impl From<&str> for DocId {
+    fn from(id: &str) -> Self {
+        DocId::from(id.to_string())
+    }
+}
+
+impl From<QuestionId> for DocId {
+    fn from(question: QuestionId) -> Self {
+        DocId::Question(question)
+    }
+}
@@ -0,0 +1,222 @@
+use crate::analyzer::Analyzer;
+use crate::document::DocId;
+use crate::keywords::KeywordMethod;
+use crate::lexer::TokenKind;
+use crate::model::Model;
+use crate::suggest::TagSuggester;
+use crate::tags::split_tags;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+/// A way of proposing tags for a question that can be evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TagMethod {
+    /// Keywords of the question itself, see [`Model::keywords`].
+    Keywords(KeywordMethod),
+    /// A [`TagSuggester`] with this many neighbours, trained on the other folds.
+    Suggester { neighbours: usize },
+}
+
+impl TagMethod {
+    pub fn name(&self) -> String {
+        match self {
+            TagMethod::Keywords(KeywordMethod::TfIdf) => "keywords (TF-IDF)".to_string(),
+            TagMethod::Keywords(KeywordMethod::TextRank) => "keywords (TextRank)".to_string(),
+            TagMethod::Suggester { neighbours } => format!("kNN suggester (k = {})", neighbours),
+        }
+    }
+}
+
+/// Mean precision, recall and F1 over a set of questions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Scores {
+    pub questions: usize,
+    pub precision: f32,
+    pub recall: f32,
+    pub f1: f32,
+}
+
+impl Scores {
+    fn add(&mut self, precision: f32, recall: f32) {
+        let f1 = if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        };
+        // Running means, so that the scores are always averages
+        self.questions += 1;
+        let n = self.questions as f32;
+        self.precision += (precision - self.precision) / n;
+        self.recall += (recall - self.recall) / n;
+        self.f1 += (f1 - self.f1) / n;
+    }
+}
+
+/// Result of [`Evaluation::run`], overall and per chapter, the first
+/// component of the section numbers.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Report {
+    pub method: TagMethod,
+    pub k: usize,
+    pub folds: usize,
+    pub overall: Scores,
+    pub chapters: BTreeMap<u32, Scores>,
+}
+
+impl Report {
+    /// The report as a Markdown table.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        let _ = writeln!(markdown, "# Tag suggestions: {}\n", self.method.name());
+        let _ = writeln!(
+            markdown,
+            "Top {} tags, {}-fold cross-validation.\n",
+            self.k, self.folds
+        );
+        let _ = writeln!(
+            markdown,
+            "| Chapter | Questions | P@{k} | R@{k} | F1@{k} |",
+            k = self.k
+        );
+        let _ = writeln!(markdown, "|---|---:|---:|---:|---:|");
+        let rows = self
+            .chapters
+            .iter()
+            .map(|(chapter, scores)| (chapter.to_string(), scores))
+            .chain([("all".to_string(), &self.overall)]);
+        for (chapter, scores) in rows {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {:.3} | {:.3} | {:.3} |",
+                chapter, scores.questions, scores.precision, scores.recall, scores.f1
+            );
+        }
+        markdown
+    }
+}
+
+/// Cross-validation of a [`TagMethod`] against the tags of the indexed
+/// questions.
+///
+/// Questions are dealt into `folds` folds by section number; a suggester is
+/// trained on all other folds for each one. Document frequencies come from
+/// the whole index, as they would for a new question added to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evaluation {
+    /// Number of proposed tags that are scored per question.
+    pub k: usize,
+    pub folds: usize,
+}
+
+impl Default for Evaluation {
+    fn default() -> Self {
+        Self { k: 5, folds: 5 }
+    }
+}
+
+/// Comparable form of a tag: its analyzed words without shingles, so that
+/// inflections and spelling variants of a tag match.
+fn tag_key<A: Analyzer>(model: &Model<A>, tag: &str) -> String {
+    model
+        .analyzer
+        .analyze(tag, model.language)
+        .into_iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Shingle | TokenKind::NGram))
+        .map(|token| token.term)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Evaluation {
+    /// Score `method` on the questions in `model` that have tags in `tags`,
+    /// the `Key,Tags` rows returned by [`crate::tags::load_csv_data`].
+    pub fn run<A: Analyzer>(
+        &self,
+        model: &Model<A>,
+        tags: &HashMap<String, String>,
+        method: TagMethod,
+    ) -> Report {
+        let folds = self.folds.max(1);
+        let mut questions: Vec<(Vec<u32>, &DocId, &str)> = model
+            .docs
+            .keys()
+            .filter_map(|id| match id {
+                DocId::Question(question) => Some((id, question.section.as_str())),
+                _ => None,
+            })
+            .filter(|(_, section)| {
+                tags.get(*section)
+                    .is_some_and(|v| !split_tags(v).is_empty())
+            })
+            .map(|(id, section)| {
+                let numbers = section.split('.').filter_map(|n| n.parse().ok()).collect();
+                (numbers, id, section)
+            })
+            .collect();
+        questions.sort();
+
+        let mut report = Report {
+            method,
+            k: self.k,
+            folds,
+            overall: Scores::default(),
+            chapters: BTreeMap::new(),
+        };
+
+        for fold in 0..folds {
+            let test: Vec<&(Vec<u32>, &DocId, &str)> =
+                questions.iter().skip(fold).step_by(folds).collect();
+            let suggester = match method {
+                TagMethod::Suggester { neighbours } => {
+                    let test_sections: HashSet<&str> = test.iter().map(|q| q.2).collect();
+                    let mut training = tags.clone();
+                    training.retain(|section, _| !test_sections.contains(section.as_str()));
+                    let mut suggester = TagSuggester::train(model, &training);
+                    suggester.neighbours = neighbours;
+                    Some(suggester)
+                }
+                TagMethod::Keywords(_) => None,
+            };
+
+            for (numbers, id, section) in test {
+                let proposed: Vec<String> = match (&suggester, method) {
+                    (Some(suggester), _) => suggester
+                        .suggest_for(model, id, self.k)
+                        .into_iter()
+                        .map(|suggestion| suggestion.tag)
+                        .collect(),
+                    (None, TagMethod::Keywords(keywords)) => model
+                        .keywords(id, self.k, keywords)
+                        .into_iter()
+                        .map(|keyword| keyword.surface)
+                        .collect(),
+                    (None, TagMethod::Suggester { .. }) => Vec::new(),
+                };
+
+                let expected: HashSet<String> = split_tags(&tags[*section])
+                    .iter()
+                    .map(|tag| tag_key(model, tag))
+                    .filter(|key| !key.is_empty())
+                    .collect();
+                let proposed: HashSet<String> =
+                    proposed.iter().map(|tag| tag_key(model, tag)).collect();
+                let hits = proposed.intersection(&expected).count() as f32;
+                let precision = hits / self.k.max(1) as f32;
+                let recall = hits / expected.len().max(1) as f32;
+
+                report.overall.add(precision, recall);
+                if let Some(&chapter) = numbers.first() {
+                    report
+                        .chapters
+                        .entry(chapter)
+                        .or_default()
+                        .add(precision, recall);
+                }
+            }
+        }
+        report
+    }
+}
@@ -0,0 +1,384 @@
+use crate::lexer::{Language, Normalization, Token, TokenKind};
+use anyhow::{Context, Result};
+use rust_stemmers::Stemmer;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+use unicode_normalization::UnicodeNormalization;
+
+static GERMAN_STOPWORDS: &[&str] = &[
+    "aber", "alle", "allem", "allen", "aller", "alles", "als", "also", "am", "an", "ander",
+    "andere", "anderem", "anderen", "anderer", "anderes", "auch", "auf", "aus", "bei", "beim",
+    "bin", "bis", "bist", "bzw", "da", "dadurch", "daher", "damit", "dann", "das", "dass", "dem",
+    "den", "denn", "der", "des", "dessen", "deshalb", "die", "dies", "diese", "diesem", "diesen",
+    "dieser", "dieses", "doch", "dort", "du", "durch", "ein", "eine", "einem", "einen", "einer",
+    "eines", "er", "es", "etwa", "etwas", "für", "ganz", "gegen", "hat", "hatte", "hier", "ich",
+    "ihm", "ihn", "ihr", "ihre", "im", "in", "indem", "ins", "ist", "je", "jede", "jedem", "jeden",
+    "jeder", "jedes", "kann", "kein", "keine", "können", "man", "mit", "muss", "nach", "nicht",
+    "noch", "nur", "ob", "oder", "ohne", "sehr", "sein", "seine", "sich", "sie", "sind", "so",
+    "sodass", "soll", "sowie", "über", "um", "und", "uns", "unter", "viel", "vom", "von", "vor",
+    "wann", "war", "was", "weil", "welche", "welchem", "welchen", "welcher", "welches", "wenn",
+    "werden", "wie", "wir", "wird", "wo", "wobei", "zu", "zum", "zur", "zwischen",
+];
+
+static ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "because",
+    "been", "before", "being", "between", "both", "but", "by", "can", "could", "did", "do", "does",
+    "each", "for", "from", "had", "has", "have", "he", "her", "his", "how", "i", "if", "in",
+    "into", "is", "it", "its", "many", "may", "more", "most", "must", "no", "not", "of", "on",
+    "only", "or", "other", "our", "should", "so", "some", "such", "than", "that", "the", "their",
+    "them", "then", "there", "these", "they", "this", "those", "through", "to", "under", "up",
+    "was", "we", "were", "what", "when", "where", "which", "while", "who", "why", "will", "with",
+    "would", "you", "your",
+];
+
+/// Built-in stopword list for `language`, empty if none is bundled.
+pub fn builtin_stopwords(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::German => GERMAN_STOPWORDS,
+        Language::English => ENGLISH_STOPWORDS,
+        _ => &[],
+    }
+}
+
+/// One stage of an analysis chain, transforming the token stream lexed from
+/// `input`, a document or query written in `language`.
+pub trait TokenFilter {
+    fn filter<'a>(
+        &self,
+        tokens: Vec<Token<'a>>,
+        input: &'a str,
+        language: Language,
+    ) -> Vec<Token<'a>>;
+}
+
+/// Token filter stage dropping stopwords and, optionally, punctuation tokens.
+///
+/// Stopwords are compared against the NFC-composed, lowercased surface text of
+/// word tokens, so the lists hold plain, unstemmed words.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StopwordFilter {
+    /// Whether the built-in list for the document language is applied.
+    pub builtin: bool,
+    /// Additional stopwords, stored unstemmed and lowercased.
+    #[serde(default)]
+    pub custom: HashSet<String>,
+    /// Drop single-character punctuation and symbol tokens.
+    pub drop_punctuation: bool,
+}
+
+impl Default for StopwordFilter {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            custom: HashSet::new(),
+            drop_punctuation: true,
+        }
+    }
+}
+
+impl StopwordFilter {
+    /// A filter that lets every token pass.
+    pub fn disabled() -> Self {
+        Self {
+            builtin: false,
+            custom: HashSet::new(),
+            drop_punctuation: false,
+        }
+    }
+
+    /// Load custom stopwords from a file with one word per line.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("could not read stopword file {:?}", path))?;
+
+        for line in data.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                self.custom.insert(line.to_lowercase());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the lowercased `word` is a stopword in `language`.
+    pub fn is_stopword(&self, word: &str, language: Language) -> bool {
+        (self.builtin && builtin_stopwords(language).contains(&word)) || self.custom.contains(word)
+    }
+
+    /// Whether `token` passes this filter.
+    pub fn accepts(&self, token: &Token, language: Language) -> bool {
+        match token.kind {
+            TokenKind::Word => {
+                let word: String = token.text.nfc().collect();
+                !self.is_stopword(&word.to_lowercase(), language)
+            }
+            TokenKind::Punctuation | TokenKind::Symbol => !self.drop_punctuation,
+            TokenKind::Number
+            | TokenKind::SectionNumber
+            | TokenKind::Quantity
+            | TokenKind::Unit
+            | TokenKind::Formula
+            | TokenKind::Variable
+            | TokenKind::NGram
+            | TokenKind::Shingle => true,
+        }
+    }
+}
+
+impl TokenFilter for StopwordFilter {
+    fn filter<'a>(
+        &self,
+        tokens: Vec<Token<'a>>,
+        _input: &'a str,
+        language: Language,
+    ) -> Vec<Token<'a>> {
+        tokens
+            .into_iter()
+            .filter(|token| self.accepts(token, language))
+            .collect()
+    }
+}
+
+/// Lowercases word terms; variables and units keep their case.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+pub struct Lowercase;
+
+impl TokenFilter for Lowercase {
+    fn filter<'a>(
+        &self,
+        mut tokens: Vec<Token<'a>>,
+        _input: &'a str,
+        _language: Language,
+    ) -> Vec<Token<'a>> {
+        for token in tokens.iter_mut().filter(|t| t.kind == TokenKind::Word) {
+            token.term = token.term.to_lowercase();
+        }
+        tokens
+    }
+}
+
+/// Applies a [`Normalization`] to word and punctuation terms.
+impl TokenFilter for Normalization {
+    fn filter<'a>(
+        &self,
+        mut tokens: Vec<Token<'a>>,
+        _input: &'a str,
+        _language: Language,
+    ) -> Vec<Token<'a>> {
+        for token in &mut tokens {
+            match token.kind {
+                TokenKind::Word => token.term = self.word(&token.term),
+                TokenKind::Punctuation | TokenKind::Symbol => {
+                    token.term = token.term.chars().map(|ch| self.punctuation(ch)).collect()
+                }
+                _ => {}
+            }
+        }
+        tokens
+    }
+}
+
+/// Stems word terms with the snowball stemmer of the document language.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+pub struct Stem;
+
+impl TokenFilter for Stem {
+    fn filter<'a>(
+        &self,
+        mut tokens: Vec<Token<'a>>,
+        _input: &'a str,
+        language: Language,
+    ) -> Vec<Token<'a>> {
+        let stemmer = Stemmer::create(language.into());
+        for token in tokens.iter_mut().filter(|t| t.kind == TokenKind::Word) {
+            token.term = stemmer.stem(&token.term).to_string();
+        }
+        tokens
+    }
+}
+
+/// Replaces word terms by the canonical term of their synonym group.
+///
+/// Terms are looked up as they arrive at this stage, so place the filter after
+/// [`Lowercase`] and [`Normalization`] but before [`Stem`]; entries are stored
+/// lowercased and normalized with the default [`Normalization`].
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct SynonymFilter {
+    synonyms: HashMap<String, String>,
+}
+
+impl SynonymFilter {
+    /// Adds a group of synonyms; the first word is the canonical one.
+    pub fn add_group<S: AsRef<str>>(&mut self, words: &[S]) {
+        let normalization = Normalization::default();
+        let mut words = words
+            .iter()
+            .map(|word| normalization.word(&word.as_ref().trim().to_lowercase()))
+            .filter(|word| !word.is_empty());
+
+        if let Some(canonical) = words.next() {
+            for word in words {
+                self.synonyms.insert(word, canonical.clone());
+            }
+        }
+    }
+
+    /// Load synonym groups from a file with one comma-separated group per line.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("could not read synonym file {:?}", path))?;
+
+        for line in data.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                self.add_group(&line.split(',').collect::<Vec<_>>());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TokenFilter for SynonymFilter {
+    fn filter<'a>(
+        &self,
+        mut tokens: Vec<Token<'a>>,
+        _input: &'a str,
+        _language: Language,
+    ) -> Vec<Token<'a>> {
+        for token in tokens.iter_mut().filter(|t| t.kind == TokenKind::Word) {
+            if let Some(canonical) = self.synonyms.get(&token.term) {
+                token.term = canonical.clone();
+            }
+        }
+        tokens
+    }
+}
+
+/// Adds character n-grams of every word term as [`TokenKind::NGram`] tokens,
+/// which lets partial words match.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct NGramFilter {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Default for NGramFilter {
+    fn default() -> Self {
+        Self { min: 3, max: 4 }
+    }
+}
+
+impl TokenFilter for NGramFilter {
+    fn filter<'a>(
+        &self,
+        tokens: Vec<Token<'a>>,
+        _input: &'a str,
+        _language: Language,
+    ) -> Vec<Token<'a>> {
+        let mut expanded = Vec::with_capacity(tokens.len());
+
+        for token in tokens {
+            let chars: Vec<char> = token.term.chars().collect();
+            let ngrams: Vec<Token<'a>> = match token.kind {
+                TokenKind::Word => (self.min..=self.max)
+                    .flat_map(|n| chars.windows(n.max(1)))
+                    .filter(|gram| gram.len() < chars.len())
+                    .map(|gram| Token {
+                        kind: TokenKind::NGram,
+                        text: token.text,
+                        term: gram.iter().collect(),
+                        span: token.span.clone(),
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            expanded.push(token);
+            expanded.extend(ngrams);
+        }
+        expanded
+    }
+}
+
+/// Adds word n-grams ("shingles") of adjacent word terms as
+/// [`TokenKind::Shingle`] tokens, so phrases such as "elektrischer Stromkreis"
+/// can be indexed and scored as a whole.
+///
+/// Any non-word token breaks a run of words, so place the filter before
+/// [`StopwordFilter`] drops punctuation. Shingles starting or ending with a
+/// stopword are skipped; stopwords inside one are kept, as in "Richtung des
+/// Stroms".
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShingleFilter {
+    pub min: usize,
+    pub max: usize,
+    /// Stopwords that may not start or end a shingle, normally the same as
+    /// the chain's stopword stage, see
+    /// [`crate::analyzer::StandardAnalyzer::set_stopwords`].
+    #[serde(default)]
+    pub stopwords: StopwordFilter,
+}
+
+impl Default for ShingleFilter {
+    fn default() -> Self {
+        Self {
+            min: 2,
+            max: 3,
+            stopwords: StopwordFilter::default(),
+        }
+    }
+}
+
+impl TokenFilter for ShingleFilter {
+    fn filter<'a>(
+        &self,
+        tokens: Vec<Token<'a>>,
+        input: &'a str,
+        language: Language,
+    ) -> Vec<Token<'a>> {
+        let is_stopword = |token: &Token| {
+            let word: String = token.text.nfc().collect();
+            self.stopwords.is_stopword(&word.to_lowercase(), language)
+        };
+
+        let mut expanded = Vec::with_capacity(tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            expanded.push(token.clone());
+            if token.kind != TokenKind::Word || is_stopword(token) {
+                continue;
+            }
+
+            for n in self.min.max(2)..=self.max {
+                let Some(words) = tokens.get(i..i + n) else {
+                    break;
+                };
+                if words.iter().any(|word| word.kind != TokenKind::Word) {
+                    break;
+                }
+                let last = &words[n - 1];
+                if is_stopword(last) {
+                    continue;
+                }
+
+                let span = token.span.start..last.span.end;
+                let term = words
+                    .iter()
+                    .map(|word| word.term.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                expanded.push(Token {
+                    kind: TokenKind::Shingle,
+                    text: &input[span.clone()],
+                    term,
+                    span,
+                });
+            }
+        }
+        expanded
+    }
+}
@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+/// Longest identifier, in characters, accepted as a formula variable.
+const MAX_VARIABLE_LEN: usize = 3;
+
+/// An inline equation such as `U = R * I`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formula {
+    /// Byte offset just past the formula.
+    pub end: usize,
+    /// Normalized form without whitespace and with unified operators, e.g. `U=R*I`.
+    pub term: String,
+    /// Byte ranges of the variables in order of appearance.
+    pub variables: Vec<Range<usize>>,
+}
+
+/// Maps an operator character onto its normalized form.
+fn operator(ch: char) -> Option<char> {
+    match ch {
+        '=' | '+' | '-' | '*' | '/' | '^' => Some(ch),
+        '·' | '⋅' | '×' => Some('*'),
+        '÷' => Some('/'),
+        '−' | '–' => Some('-'),
+        _ => None,
+    }
+}
+
+/// Whether `text` looks like a formula variable: a single letter, optionally
+/// followed by a numeric index such as `R1`.
+pub fn is_variable(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(char::is_alphabetic) && chars.all(|c| c.is_ascii_digit())
+}
+
+/// Cursor over the input used while scanning a formula.
+struct Scanner<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    /// Skips spaces and tabs, but never a line break.
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\u{a0}' | '\u{202f}')) {
+            self.bump();
+        }
+    }
+
+    /// Consumes characters while `cond` holds and returns the consumed range.
+    fn take_while(&mut self, cond: impl Fn(char) -> bool) -> Range<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(&cond) {
+            self.bump();
+        }
+        start..self.pos
+    }
+}
+
+/// Scans an equation starting with a variable at byte offset `start`.
+///
+/// A formula is a sequence of variables and numbers joined by operators and
+/// optionally grouped by parentheses; it must contain `=` and may not span
+/// lines. Returns `None` if no such formula starts at `start`.
+pub fn scan(input: &str, start: usize) -> Option<Formula> {
+    let mut scanner = Scanner { input, pos: start };
+    let mut term = String::new();
+    let mut variables = Vec::new();
+    let mut depth = 0usize;
+    let mut has_equals = false;
+    // (end, term length, variable count) of the longest complete formula
+    let mut complete = None;
+
+    loop {
+        // Operand, with any opening parentheses in front of it
+        scanner.skip_spaces();
+        while scanner.peek() == Some('(') {
+            scanner.bump();
+            term.push('(');
+            depth += 1;
+            scanner.skip_spaces();
+        }
+
+        match scanner.peek() {
+            Some(ch) if ch.is_alphabetic() => {
+                let range = scanner.take_while(|c| c.is_alphanumeric());
+                if input[range.clone()].chars().count() > MAX_VARIABLE_LEN {
+                    break;
+                }
+                term.push_str(&input[range.clone()]);
+                variables.push(range);
+            }
+            Some(ch) if ch.is_ascii_digit() => {
+                let mut range = scanner.take_while(|c| c.is_ascii_digit());
+                let rest = &input[scanner.pos..];
+                if rest.starts_with([',', '.'])
+                    && rest[1..].starts_with(|c: char| c.is_ascii_digit())
+                {
+                    scanner.bump();
+                    range.end = scanner.take_while(|c| c.is_ascii_digit()).end;
+                }
+                term.push_str(&input[range].replace(',', "."));
+            }
+            _ => break,
+        }
+
+        // Closing parentheses directly after the operand
+        while depth > 0 && scanner.peek() == Some(')') {
+            scanner.bump();
+            term.push(')');
+            depth -= 1;
+        }
+
+        if depth == 0 && has_equals {
+            complete = Some((scanner.pos, term.len(), variables.len()));
+        }
+
+        // Operator joining the next operand
+        scanner.skip_spaces();
+        match scanner.peek().and_then(operator) {
+            Some(op) => {
+                scanner.bump();
+                has_equals |= op == '=';
+                term.push(op);
+            }
+            None => break,
+        }
+    }
+
+    let (end, term_len, variable_count) = complete?;
+    term.truncate(term_len);
+    variables.truncate(variable_count);
+    Some(Formula {
+        end,
+        term,
+        variables,
+    })
+}
@@ -0,0 +1,121 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+/// Factor applied to the score of a fuzzy match per edit.
+pub const FUZZY_WEIGHT: f32 = 0.5;
+
+/// Levenshtein distance between `a` and `b` in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Edits tolerated for `term` when no distance is given: none for short
+/// terms, where a single edit already changes the meaning, two for long ones.
+pub fn auto_distance(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Whether `term` is worth matching fuzzily: a single word of letters.
+pub fn is_fuzzy_candidate(term: &str) -> bool {
+    !term.is_empty() && term.chars().all(char::is_alphabetic)
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    term: String,
+    /// Children keyed by their distance to `term`.
+    children: HashMap<usize, Node>,
+}
+
+/// BK-tree over terms, finding all terms within an edit distance without
+/// comparing against the whole vocabulary.
+#[derive(Debug, Clone, Default)]
+pub struct BkTree {
+    root: Option<Node>,
+    len: usize,
+}
+
+impl BkTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Add `term`; adding a term twice has no effect.
+    pub fn insert(&mut self, term: &str) {
+        let Some(mut node) = self.root.as_mut() else {
+            self.root = Some(Node {
+                term: term.to_string(),
+                children: HashMap::new(),
+            });
+            self.len += 1;
+            return;
+        };
+
+        loop {
+            let distance = levenshtein(term, &node.term);
+            if distance == 0 {
+                return;
+            }
+            match node.children.entry(distance) {
+                Entry::Occupied(child) => node = child.into_mut(),
+                Entry::Vacant(slot) => {
+                    slot.insert(Node {
+                        term: term.to_string(),
+                        children: HashMap::new(),
+                    });
+                    self.len += 1;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// All terms within `max_distance` edits of `term` with their distance,
+    /// closest first.
+    pub fn find(&self, term: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let mut found = Vec::new();
+        let mut pending: Vec<&Node> = self.root.iter().collect();
+
+        while let Some(node) = pending.pop() {
+            let distance = levenshtein(term, &node.term);
+            if distance <= max_distance {
+                found.push((node.term.as_str(), distance));
+            }
+            // By the triangle inequality only these subtrees can hold matches
+            let range = distance.saturating_sub(max_distance)..=distance + max_distance;
+            pending.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| range.contains(d))
+                    .map(|(_, child)| child),
+            );
+        }
+
+        found.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(b.0)));
+        found
+    }
+}
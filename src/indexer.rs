@@ -0,0 +1,219 @@
+use crate::analyzer::Analyzer;
+use crate::document::{meta, DocId, Metadata};
+use crate::model::Model;
+use crate::question::{split_questions, QuestionId, QUESTION_PREFIX};
+use crate::tree::FileTree;
+use anyhow::{Context, Result};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Changes applied to a model by [`Indexer::index`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IndexReport {
+    /// Files that were not indexed before.
+    pub added: Vec<PathBuf>,
+    /// Files re-indexed because they changed since they were last indexed.
+    pub updated: Vec<PathBuf>,
+    /// Documents dropped because their file no longer exists.
+    pub removed: Vec<PathBuf>,
+    /// Number of files skipped because they are up to date.
+    pub unchanged: usize,
+}
+
+impl IndexReport {
+    /// Whether the model was modified.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Keeps a model in sync with a directory of extracted pages.
+#[derive(Debug, Clone)]
+pub struct Indexer {
+    /// Only files whose name starts with one of these prefixes are indexed.
+    pub prefixes: Vec<String>,
+    /// Only files with this extension are indexed.
+    pub extension: String,
+    /// Descend into subdirectories.
+    pub recursive: bool,
+    /// Page tree used for the breadcrumb metadata of documents.
+    pub tree: Option<FileTree>,
+}
+
+impl Default for Indexer {
+    /// Matches the `question-<id>-<section>.txt` files written by the spider,
+    /// and `extracted-<id>.txt` dumps of pages without questions.
+    fn default() -> Self {
+        Self {
+            prefixes: vec![QUESTION_PREFIX.to_string(), "extracted-".to_string()],
+            extension: "txt".to_string(),
+            recursive: false,
+            tree: None,
+        }
+    }
+}
+
+impl Indexer {
+    /// Whether the file at `path` is one this indexer is responsible for.
+    pub fn matches(&self, path: &Path) -> bool {
+        let name_matches = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| self.prefixes.iter().any(|prefix| name.starts_with(prefix)));
+        let extension_matches = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext == self.extension);
+        name_matches && extension_matches
+    }
+
+    /// Document id of the file at `path`: a question for question files, a
+    /// tree node for page dumps, and the file itself for anything else.
+    pub fn document_id(path: &Path) -> DocId {
+        if let Some(question) = QuestionId::from_path(path) {
+            return DocId::Question(question);
+        }
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix("extracted-"))
+            .filter(|node_id| !node_id.is_empty())
+            .map_or_else(
+                || DocId::File(path.to_path_buf()),
+                |node_id| DocId::Node(node_id.to_string()),
+            )
+    }
+
+    /// Metadata of the document `id` read from `path` with `content`.
+    fn metadata<A>(&self, model: &Model<A>, id: &DocId, path: &Path, content: &str) -> Metadata {
+        let mut metadata = Metadata::new();
+        metadata.insert(meta::PATH.to_string(), path.display().to_string());
+
+        let node_id = match id {
+            DocId::Question(question) => Some(question.node_id.as_str()),
+            DocId::Node(node_id) => Some(node_id.as_str()),
+            DocId::File(_) => None,
+        };
+        if let Some(node_id) = node_id {
+            metadata.insert(meta::NODE.to_string(), node_id.to_string());
+            let breadcrumb = self.tree.as_ref().and_then(|tree| tree.breadcrumb(node_id));
+            if let Some(breadcrumb) = breadcrumb {
+                metadata.insert(meta::BREADCRUMB.to_string(), breadcrumb.join(" > "));
+            }
+        }
+
+        if let DocId::Question(question) = id {
+            metadata.insert(meta::SECTION.to_string(), question.section.clone());
+            let title = split_questions(content)
+                .into_iter()
+                .find(|q| q.section == question.section)
+                .map(|q| q.title);
+            if let Some(title) = title {
+                metadata.insert(meta::TITLE.to_string(), title);
+            }
+        }
+        Self::set_tags(model, id, &mut metadata);
+        metadata
+    }
+
+    /// Set the tags metadata of a question from the model's current tags.
+    fn set_tags<A>(model: &Model<A>, id: &DocId, metadata: &mut Metadata) {
+        let DocId::Question(question) = id else {
+            return;
+        };
+        match model.tags.get(&question.section) {
+            Some(tags) => metadata.insert(meta::TAGS.to_string(), tags.join(", ")),
+            None => metadata.remove(meta::TAGS),
+        };
+    }
+
+    /// Index all matching files below `dir`, skipping files whose modification
+    /// time is not newer than the indexed one, and drop documents under `dir`
+    /// whose file has disappeared. Question tags are taken from the model, see
+    /// [`Model::set_tags`].
+    pub fn index<A: Analyzer>(&self, model: &mut Model<A>, dir: &Path) -> Result<IndexReport> {
+        let mut report = IndexReport::default();
+        let mut seen = HashSet::new();
+
+        for path in self.collect_files(dir)? {
+            let last_modified = fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .with_context(|| format!("could not read modification time of {:?}", path))?;
+            let id = Self::document_id(&path);
+            seen.insert(path.clone());
+
+            if !model.requires_reindexing(&id, last_modified) {
+                // Tags may have changed without the file changing
+                if let Some(mut metadata) = model.metadata(&id).cloned() {
+                    Self::set_tags(model, &id, &mut metadata);
+                    model.set_metadata(&id, metadata);
+                }
+                report.unchanged += 1;
+                continue;
+            }
+
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("could not read extracted file {:?}", path))?;
+            if model.docs.contains_key(&id) {
+                report.updated.push(path.clone());
+            } else {
+                report.added.push(path.clone());
+            }
+            let metadata = self.metadata(model, &id, &path, &content);
+            model.add_document(id.clone(), last_modified, &content);
+            model.set_metadata(&id, metadata);
+        }
+
+        let stale: Vec<(DocId, PathBuf)> = model
+            .docs
+            .keys()
+            .filter_map(|id| {
+                let path = match model.metadata(id).and_then(|m| m.get(meta::PATH)) {
+                    Some(path) => PathBuf::from(path),
+                    None => match id {
+                        DocId::File(path) => path.clone(),
+                        _ => return None,
+                    },
+                };
+                let stale = path.starts_with(dir) && self.matches(&path) && !seen.contains(&path);
+                stale.then(|| (id.clone(), path))
+            })
+            .collect();
+        for (id, path) in stale {
+            model.remove_document(&id);
+            report.removed.push(path);
+        }
+
+        report.added.sort();
+        report.updated.sort();
+        report.removed.sort();
+        Ok(report)
+    }
+
+    /// Matching files below `dir`, in no particular order.
+    fn collect_files(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            let entries = fs::read_dir(&dir)
+                .with_context(|| format!("could not read directory {:?}", dir))?;
+            for entry in entries {
+                let entry = entry?;
+                let path = entry.path();
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    if self.recursive {
+                        pending.push(path);
+                    }
+                } else if self.matches(&path) {
+                    files.push(path);
+                }
+            }
+        }
+
+        Ok(files)
+    }
+}
@@ -0,0 +1,147 @@
+use crate::lexer::Language;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Words of a TextRank phrase at most.
+pub const MAX_PHRASE_WORDS: usize = 3;
+
+/// Words on either side of a word that count as co-occurring with it.
+const WINDOW: usize = 2;
+/// PageRank damping factor.
+const DAMPING: f32 = 0.85;
+const ITERATIONS: usize = 30;
+
+/// How [`crate::model::Model::keywords`] ranks the terms of a document.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum KeywordMethod {
+    /// Indexed terms and shingles by TF-IDF, so terms common in the document
+    /// but rare in the index come first.
+    #[default]
+    TfIdf,
+    /// Phrases of adjacent content words, RAKE-style, scored by the TextRank
+    /// of their words in the document's co-occurrence graph, times their IDF.
+    TextRank,
+}
+
+/// A distinctive term or phrase of a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyword {
+    /// The indexed term; words of a phrase are separated by spaces.
+    pub term: String,
+    /// Readable spelling as it appears in the documents.
+    pub surface: String,
+    pub score: f32,
+}
+
+/// Whether `term` can be offered as a keyword: one or more words of at least
+/// three letters, so numbers, units and formulas are left out.
+pub fn is_keyword_candidate(term: &str) -> bool {
+    !term.is_empty()
+        && term
+            .split(' ')
+            .all(|word| word.chars().count() >= 3 && word.chars().all(char::is_alphabetic))
+}
+
+/// Endings of inflected German adjectives, as in `ohmsche`, `elektrischen`.
+const ADJECTIVE_ENDINGS: [&str; 5] = ["e", "em", "en", "er", "es"];
+
+/// Whether the readable `surface` of a keyword is a noun phrase, as tags are:
+/// a noun, optionally preceded by nouns and adjectives. German nouns are
+/// capitalized and attributive adjectives inflected, which rules out the
+/// verbs and adverbs not covered by the stopwords; other languages are not
+/// checked.
+pub fn is_noun_phrase(surface: &str, language: Language) -> bool {
+    let capitalized = |word: &str| word.chars().next().is_some_and(char::is_uppercase);
+    match language {
+        Language::German => {
+            let words: Vec<&str> = surface.split_whitespace().collect();
+            let Some((head, modifiers)) = words.split_last() else {
+                return false;
+            };
+            capitalized(head)
+                && modifiers.iter().all(|word| {
+                    capitalized(word)
+                        || ADJECTIVE_ENDINGS
+                            .iter()
+                            .any(|ending| word.ends_with(ending))
+                })
+        }
+        _ => true,
+    }
+}
+
+/// Whether the words of `a` and `b` overlap such that one of them adds
+/// nothing next to the other, e.g. `widerstand` and `ohmsch widerstand`.
+pub fn is_redundant(a: &str, b: &str) -> bool {
+    let contains = |long: &str, short: &str| {
+        let long: Vec<&str> = long.split(' ').collect();
+        let short: Vec<&str> = short.split(' ').collect();
+        long.windows(short.len())
+            .any(|window| window == short.as_slice())
+    };
+    contains(a, b) || contains(b, a)
+}
+
+/// TextRank of the words in `runs`, runs of adjacent words not separated by
+/// punctuation or stopwords. Words within [`WINDOW`] positions of each other
+/// in the same run are linked.
+pub fn text_rank(runs: &[Vec<&str>]) -> HashMap<String, f32> {
+    let mut links: HashMap<&str, HashMap<&str, f32>> = HashMap::new();
+    for run in runs {
+        for (i, &word) in run.iter().enumerate() {
+            links.entry(word).or_default();
+            for &other in run.iter().skip(i + 1).take(WINDOW) {
+                if other != word {
+                    *links.entry(word).or_default().entry(other).or_insert(0.0) += 1.0;
+                    *links.entry(other).or_default().entry(word).or_insert(0.0) += 1.0;
+                }
+            }
+        }
+    }
+
+    let weight_sums: HashMap<&str, f32> = links
+        .iter()
+        .map(|(&word, neighbours)| (word, neighbours.values().sum()))
+        .collect();
+    let mut ranks: HashMap<&str, f32> = links.keys().map(|&word| (word, 1.0)).collect();
+    for _ in 0..ITERATIONS {
+        ranks = links
+            .iter()
+            .map(|(&word, neighbours)| {
+                let incoming: f32 = neighbours
+                    .iter()
+                    .map(|(&other, &weight)| weight / weight_sums[other] * ranks[other])
+                    .sum();
+                (word, 1.0 - DAMPING + DAMPING * incoming)
+            })
+            .collect();
+    }
+
+    ranks
+        .into_iter()
+        .map(|(word, rank)| (word.to_string(), rank))
+        .collect()
+}
+
+/// Candidate phrases of up to [`MAX_PHRASE_WORDS`] adjacent words of `runs`
+/// as the index of the run and the range of words in it, scored by the mean
+/// of their word `ranks`, so a weak word drags a phrase below its parts.
+pub fn rank_phrases(
+    runs: &[Vec<&str>],
+    ranks: &HashMap<String, f32>,
+) -> Vec<(usize, Range<usize>, f32)> {
+    let mut phrases = Vec::new();
+    for (index, run) in runs.iter().enumerate() {
+        for start in 0..run.len() {
+            for end in start + 1..=run.len().min(start + MAX_PHRASE_WORDS) {
+                let sum: f32 = run[start..end]
+                    .iter()
+                    .map(|word| ranks.get(*word).copied().unwrap_or(0.0))
+                    .sum();
+                phrases.push((index, start..end, sum / (end - start) as f32));
+            }
+        }
+    }
+    phrases
+}
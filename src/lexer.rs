@@ -1,6 +1,173 @@
+use crate::formula;
+use crate::units::Unit;
 use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::iter::Peekable;
+use std::ops::Range;
 use std::str::CharIndices;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Stemming language, one per snowball algorithm offered by `rust_stemmers`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Language {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    #[default]
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl From<Language> for Algorithm {
+    fn from(language: Language) -> Self {
+        match language {
+            Language::Arabic => Algorithm::Arabic,
+            Language::Danish => Algorithm::Danish,
+            Language::Dutch => Algorithm::Dutch,
+            Language::English => Algorithm::English,
+            Language::Finnish => Algorithm::Finnish,
+            Language::French => Algorithm::French,
+            Language::German => Algorithm::German,
+            Language::Greek => Algorithm::Greek,
+            Language::Hungarian => Algorithm::Hungarian,
+            Language::Italian => Algorithm::Italian,
+            Language::Norwegian => Algorithm::Norwegian,
+            Language::Portuguese => Algorithm::Portuguese,
+            Language::Romanian => Algorithm::Romanian,
+            Language::Russian => Algorithm::Russian,
+            Language::Spanish => Algorithm::Spanish,
+            Language::Swedish => Algorithm::Swedish,
+            Language::Tamil => Algorithm::Tamil,
+            Language::Turkish => Algorithm::Turkish,
+        }
+    }
+}
+
+/// Normalization applied to token terms; the surface text is never altered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Normalization {
+    /// Compose terms to Unicode NFC, so precomposed and decomposed umlauts match.
+    pub nfc: bool,
+    /// Fold `ä`, `ö`, `ü` to `ae`, `oe`, `ue` and `ß` to `ss`.
+    pub fold_umlauts: bool,
+    /// Map typographic quotes and dashes onto `"`, `'` and `-`.
+    pub unify_punctuation: bool,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            nfc: true,
+            fold_umlauts: true,
+            unify_punctuation: true,
+        }
+    }
+}
+
+impl Normalization {
+    /// Leaves terms exactly as lexed.
+    pub fn none() -> Self {
+        Self {
+            nfc: false,
+            fold_umlauts: false,
+            unify_punctuation: false,
+        }
+    }
+
+    /// Normalize a lowercased word before stemming.
+    pub fn word(&self, word: &str) -> String {
+        let word: String = if self.nfc {
+            word.nfc().collect()
+        } else {
+            word.to_string()
+        };
+        if self.fold_umlauts {
+            fold_umlauts(&word)
+        } else {
+            word
+        }
+    }
+
+    /// Normalize a punctuation or symbol character.
+    pub fn punctuation(&self, ch: char) -> char {
+        if !self.unify_punctuation {
+            return ch;
+        }
+        match ch {
+            '„' | '“' | '”' | '«' | '»' => '"',
+            '‚' | '‘' | '’' => '\'',
+            '‐' | '‒' | '–' | '—' | '−' => '-',
+            _ => ch,
+        }
+    }
+}
+
+/// Replace German umlauts and `ß` with their two-letter spellings.
+pub fn fold_umlauts(word: &str) -> String {
+    let mut folded = String::with_capacity(word.len());
+    for ch in word.chars() {
+        match ch {
+            'ä' => folded.push_str("ae"),
+            'ö' => folded.push_str("oe"),
+            'ü' => folded.push_str("ue"),
+            'Ä' => folded.push_str("Ae"),
+            'Ö' => folded.push_str("Oe"),
+            'Ü' => folded.push_str("Ue"),
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            _ => folded.push(ch),
+        }
+    }
+    folded
+}
+
+/// Category of a lexed token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum TokenKind {
+    Word,
+    Number,
+    /// Hierarchical question key such as `1.1.12`.
+    SectionNumber,
+    /// A number with a unit, such as `4,7 kΩ`.
+    Quantity,
+    /// A unit symbol or name, such as `mA` or `Kiloohm`.
+    Unit,
+    /// An inline equation, normalized like `U=R*I`.
+    Formula,
+    /// A case-preserving formula variable such as `U` or `R1`.
+    Variable,
+    /// A character n-gram of a word, added by an analyzer filter.
+    NGram,
+    /// A phrase of adjacent words, added by an analyzer filter.
+    Shingle,
+    Punctuation,
+    Symbol,
+}
+
+/// A token with its original surface text, normalized term and byte range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// The text exactly as it appears in the input.
+    pub text: &'a str,
+    /// The normalized (lowercased, folded, stemmed) form used for indexing.
+    pub term: String,
+    /// Byte range of `text` in the input.
+    pub span: Range<usize>,
+}
 
 pub struct Lexer<'a> {
     input: &'a str,
@@ -8,19 +175,58 @@ pub struct Lexer<'a> {
     start: usize,
     end: usize,
     stemmer: Stemmer,
+    normalization: Normalization,
+    /// Whether equations and variables are recognized.
+    formulas: bool,
+    /// Whether word and punctuation terms are left as written.
+    raw: bool,
+    /// Tokens already lexed but not yet returned.
+    pending: VecDeque<Token<'a>>,
 }
 
 impl<'a> Lexer<'a> {
+    /// Creates a lexer stemming with the default language (German).
     pub fn new(input: &'a str) -> Self {
+        Self::with_language(input, Language::default())
+    }
+
+    /// Creates a lexer stemming alphabetic runs with the given language.
+    pub fn with_language(input: &'a str, language: Language) -> Self {
         Lexer {
             input,
             chars: input.char_indices().peekable(),
             start: 0,
             end: 0,
-            stemmer: Stemmer::create(Algorithm::English),
+            stemmer: Stemmer::create(language.into()),
+            normalization: Normalization::default(),
+            formulas: false,
+            raw: false,
+            pending: VecDeque::new(),
         }
     }
 
+    /// Replaces the default term normalization.
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Enables formula-aware lexing: inline equations become a formula token
+    /// followed by their variables, and lone letters such as `Q` or `R1` are
+    /// kept as case-preserving variables instead of being lowercased and stemmed.
+    pub fn with_formulas(mut self, formulas: bool) -> Self {
+        self.formulas = formulas;
+        self
+    }
+
+    /// Leaves word and punctuation terms exactly as written, without
+    /// lowercasing, normalization or stemming, so an analyzer can apply those
+    /// steps as separate filters.
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+
     /// Skips any leading whitespace characters.
     fn trim_left(&mut self) {
         while let Some(&(_, ch)) = self.chars.peek() {
@@ -59,38 +265,293 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Consumes a number starting at the next character. Dot-separated digit
+    /// groups (`1.1.12`) and a single decimal comma (`1,5`) are joined into it;
+    /// a trailing separator or a range dash is left for the next token.
+    /// Returns the number of dots and commas consumed.
+    fn chop_number(&mut self) -> (usize, usize) {
+        self.chop_while(|c| c.is_ascii_digit());
+        let (mut dots, mut commas) = (0, 0);
+
+        loop {
+            let mut ahead = self.input[self.end..].chars();
+            match (ahead.next(), ahead.next()) {
+                (Some('.'), Some(d)) if d.is_ascii_digit() && commas == 0 => dots += 1,
+                (Some(','), Some(d)) if d.is_ascii_digit() && commas == 0 && dots == 0 => {
+                    commas += 1
+                }
+                _ => break,
+            }
+
+            // Consume the separator, then the following digit group
+            self.chars.next();
+            while let Some(&(idx, c)) = self.chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                self.chars.next();
+                self.end = idx + c.len_utf8();
+            }
+        }
+
+        (dots, commas)
+    }
+
+    /// Looks for a unit symbol or name following the current number, separated
+    /// by at most some horizontal whitespace. Returns the unit and its byte range.
+    fn peek_unit(&self) -> Option<(Unit, Range<usize>)> {
+        let rest = &self.input[self.end..];
+        let gap = rest.len() - rest.trim_start_matches([' ', '\u{a0}', '\u{202f}']).len();
+        let start = self.end + gap;
+
+        let len = self.input[start..]
+            .find(|c: char| !(c.is_alphabetic() || is_combining_mark(c)))
+            .unwrap_or(self.input.len() - start);
+        let end = start + len;
+        if len == 0 || self.input[end..].starts_with(|c: char| c.is_alphanumeric()) {
+            return None;
+        }
+
+        let text = &self.input[start..end];
+        Unit::from_symbol(text, false)
+            .or_else(|| Unit::from_name(&text.to_lowercase()))
+            .map(|unit| (unit, start..end))
+    }
+
+    /// Consumes characters up to the byte offset `end`, extending the current token.
+    fn advance_to(&mut self, end: usize) {
+        while self.chars.next_if(|&(idx, _)| idx < end).is_some() {}
+        self.end = end;
+    }
+
+    /// Queues unit tokens for `unit` at `span`: the full unit, then the bare
+    /// base unit if it carries a prefix, so `mA` can be found by `Ampere`.
+    fn queue_unit(&mut self, unit: Unit, span: Range<usize>) {
+        let text = &self.input[span.clone()];
+        self.pending.push_back(Token {
+            kind: TokenKind::Unit,
+            text,
+            term: unit.name(),
+            span: span.clone(),
+        });
+        if unit.prefix.is_some() {
+            self.pending.push_back(Token {
+                kind: TokenKind::Unit,
+                text,
+                term: unit.base.to_string(),
+                span,
+            });
+        }
+    }
+
+    /// Lexes an equation starting at byte offset `start`, queueing its variables.
+    /// A unit after a final number, as in `U = 230 V`, is queued as a quantity
+    /// of that number rather than left to be read as a variable.
+    fn lex_formula(&mut self, start: usize) -> Option<Token<'a>> {
+        let formula = formula::scan(self.input, start)?;
+        self.start = start;
+        self.advance_to(formula.end);
+
+        let token = self.make_token(TokenKind::Formula, formula.term);
+        for span in formula.variables {
+            let text = &self.input[span.clone()];
+            self.pending.push_back(Token {
+                kind: TokenKind::Variable,
+                text,
+                term: text.nfc().collect(),
+                span,
+            });
+        }
+
+        let before = &self.input[..formula.end];
+        let number_start = before
+            .trim_end_matches(|c: char| c.is_ascii_digit() || c == ',' || c == '.')
+            .len();
+        if before[number_start..].starts_with(|c: char| c.is_ascii_digit()) {
+            if let Some((unit, span)) = self.peek_unit() {
+                let number = before[number_start..].replace(',', ".");
+                self.pending.push_back(Token {
+                    kind: TokenKind::Quantity,
+                    text: &self.input[number_start..span.end],
+                    term: format!("{number} {}", unit.name()),
+                    span: number_start..span.end,
+                });
+                self.queue_unit(unit, span.clone());
+                self.advance_to(span.end);
+            }
+        }
+        Some(token)
+    }
+
+    /// Builds a token of `kind` from the current slice.
+    fn make_token(&self, kind: TokenKind, term: String) -> Token<'a> {
+        Token {
+            kind,
+            text: self.slice_current(),
+            term,
+            span: self.start..self.end,
+        }
+    }
+
     /// Retrieves the next token, performing stemming on alphabetic runs.
-    pub fn next_token(&mut self) -> Option<String> {
+    pub fn next_token(&mut self) -> Option<Token<'a>> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+
         self.trim_left();
         let &(_, ch) = self.chars.peek()?;
 
         if ch.is_ascii_digit() {
-            self.chop_while(|c| c.is_ascii_digit());
-            return Some(self.slice_current().to_string());
+            let (dots, _) = self.chop_number();
+            let text = self.slice_current();
+            if dots >= 2 && is_section_number(text) {
+                return Some(self.make_token(TokenKind::SectionNumber, text.to_string()));
+            }
+            // German thousands separators are dropped, decimal commas are
+            // normalized to a decimal point
+            let term = if dots >= 2 {
+                text.replace('.', "")
+            } else {
+                text.replace(',', ".")
+            };
+
+            // A number directly followed by a unit forms a quantity
+            if let Some((unit, span)) = self.peek_unit() {
+                self.advance_to(span.end);
+                let quantity =
+                    self.make_token(TokenKind::Quantity, format!("{term} {}", unit.name()));
+                self.queue_unit(unit, span);
+                return Some(quantity);
+            }
+            return Some(self.make_token(TokenKind::Number, term));
+        }
+
+        if ch.is_alphabetic() && self.formulas {
+            let &(idx, _) = self.chars.peek()?;
+            if let Some(token) = self.lex_formula(idx) {
+                return Some(token);
+            }
         }
 
         if ch.is_alphabetic() {
-            self.chop_while(|c| c.is_alphanumeric());
-            let term = self.slice_current().to_lowercase();
+            // Combining marks belong to the word, so decomposed umlauts stay whole
+            self.chop_while(|c| c.is_alphanumeric() || is_combining_mark(c));
+            let text = self.slice_current();
+            let unit = Unit::from_symbol(text, true)
+                .or_else(|| Unit::from_name(&text.nfc().collect::<String>().to_lowercase()));
+            if let Some(unit) = unit {
+                self.queue_unit(unit, self.start..self.end);
+                return self.pending.pop_front();
+            }
+            if self.formulas && formula::is_variable(text) {
+                return Some(self.make_token(TokenKind::Variable, text.nfc().collect()));
+            }
+
+            if self.raw {
+                return Some(self.make_token(TokenKind::Word, text.to_string()));
+            }
+            let term = self.normalization.word(&text.to_lowercase());
             let stemmed = self.stemmer.stem(&term).to_string();
-            return Some(stemmed);
+            return Some(self.make_token(TokenKind::Word, stemmed));
         }
 
         // Any other single character (punctuation, symbol)
         if let Some((idx, ch)) = self.chars.next() {
             self.start = idx;
             self.end = idx + ch.len_utf8();
-            return Some(ch.to_string());
+            let kind = if is_punctuation(ch) {
+                TokenKind::Punctuation
+            } else {
+                TokenKind::Symbol
+            };
+            let term = if self.raw {
+                ch
+            } else {
+                self.normalization.punctuation(ch)
+            };
+            return Some(self.make_token(kind, term.to_string()));
         }
 
         None
     }
+
+    /// Turns the lexer into an iterator over typed tokens.
+    pub fn tokens(self) -> Tokens<'a> {
+        Tokens(self)
+    }
 }
 
+/// Convenience iterator yielding only the normalized terms.
 impl Iterator for Lexer<'_> {
     type Item = String;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.next_token()
+        self.next_token().map(|token| token.term)
+    }
+}
+
+/// Iterator over the typed tokens of a [`Lexer`].
+pub struct Tokens<'a>(Lexer<'a>);
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_token()
+    }
+}
+
+/// Whether `text` is a section number of at least three dot-separated digit
+/// groups, the form of the keys in `resources/tags.csv`. Numbers written with
+/// thousands separators such as `1.234.567`, where every group after the first
+/// has three digits, and dates such as `12.03.2024` are not: section numbers
+/// have no leading zeros and no four-digit last group.
+pub fn is_section_number(text: &str) -> bool {
+    let groups: Vec<&str> = text.split('.').collect();
+    let thousands = groups[1..].iter().all(|group| group.len() == 3);
+    groups.len() >= 3
+        && !thousands
+        && groups.last().is_some_and(|group| group.len() < 4)
+        && groups.iter().all(|group| {
+            !group.is_empty()
+                && !group.starts_with('0')
+                && group.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// Whether `ch` separates or encloses text rather than carrying meaning itself.
+fn is_punctuation(ch: char) -> bool {
+    ".,;:!?()[]{}\"'-‐‒–—…„“”‚‘’«»".contains(ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_section_numbers_from_numbers_and_dates() {
+        assert!(is_section_number("1.1.12"));
+        assert!(is_section_number("1.1.100"));
+        assert!(!is_section_number("1.000.000"));
+        assert!(!is_section_number("1.234.567"));
+        assert!(!is_section_number("12.03.2024"));
+
+        let token = Lexer::new("1.234.567").next_token().unwrap();
+        assert_eq!(token.kind, TokenKind::Number);
+        assert_eq!(token.term, "1234567");
+    }
+
+    #[test]
+    fn keeps_quantity_at_end_of_formula() {
+        let tokens: Vec<(TokenKind, String)> = Lexer::new("Die Spannung U = 230 V ist")
+            .with_formulas(true)
+            .tokens()
+            .map(|token| (token.kind, token.term))
+            .collect();
+        assert!(tokens.contains(&(TokenKind::Formula, "U=230".to_string())));
+        assert!(tokens.contains(&(TokenKind::Quantity, "230 volt".to_string())));
+        assert!(tokens.contains(&(TokenKind::Unit, "volt".to_string())));
+        assert!(!tokens.contains(&(TokenKind::Variable, "V".to_string())));
     }
 }
@@ -1,6 +1,24 @@
 // src/lib.rs
+pub mod analyzer;
+pub mod complete;
+pub mod compound;
+pub mod document;
+pub mod evaluate;
 pub mod filenode;
+pub mod filter;
+pub mod formula;
+pub mod fuzzy;
+pub mod indexer;
+pub mod keywords;
 pub mod lexer;
 pub mod model;
+pub mod query;
+pub mod question;
+pub mod scorer;
+pub mod snippet;
 pub mod spider;
+pub mod storage;
+pub mod suggest;
+pub mod tags;
 pub mod tree;
+pub mod units;
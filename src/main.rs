@@ -1,15 +1,49 @@
 // src/main.rs
-use anyhow::{Context, Result};
-use crossterm::event::{Event, KeyCode};
-use csv::Reader;
-use std::path::PathBuf;
-use std::{collections::HashMap, fs, time::Duration};
+use anyhow::{bail, Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, time::Duration};
+use tag_spider_rs::complete::Completer;
+use tag_spider_rs::document::{meta, DocId};
+use tag_spider_rs::evaluate::{Evaluation, TagMethod};
+use tag_spider_rs::indexer::Indexer;
+use tag_spider_rs::keywords::KeywordMethod;
+use tag_spider_rs::model::Model;
+use tag_spider_rs::question::split_page_file;
+use tag_spider_rs::snippet::Highlight;
 use tag_spider_rs::spider::Spider;
+use tag_spider_rs::storage::IndexFormat;
+use tag_spider_rs::suggest::{TagSuggester, DEFAULT_NEIGHBOURS};
+use tag_spider_rs::tags::load_csv_data;
 use tag_spider_rs::tree::FileTree;
 use thirtyfour::{prelude::*, support, By, WebDriver};
 
 static URL: &str = "https://cms.schrackforstudents.com/neos/login";
 static TAGPATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tags.csv");
+static TREEPATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/tree.json");
+
+static USAGE: &str = r#"Usage:
+    tag-spider-rs                        run the spider (needs a running WebDriver)
+    tag-spider-rs split <page>...        split extracted page dumps into question files
+    tag-spider-rs index <dir> <index>    index the extracted files in <dir> into <index>
+    tag-spider-rs complete <index>       search <index>, completing words while typing
+    tag-spider-rs draft-tags <index> [tfidf|textrank]
+                                         print a Key,Tags CSV of suggested tags for the
+                                         questions in <index> without tags
+    tag-spider-rs train-tags <index> <suggester>
+                                         learn tags from the tagged questions in <index>
+    tag-spider-rs suggest-tags <index> <suggester>
+                                         print ranked tags for the untagged questions
+    tag-spider-rs evaluate <index> <report> [knn|tfidf|textrank] [k]
+                                         cross-validate a tagging method against the
+                                         tagged questions, writing a .md or .json report
+"#;
+
+/// Keywords suggested per question by `draft-tags` and `suggest-tags`.
+const DRAFT_TAGS: usize = 8;
 
 #[derive(serde::Deserialize)]
 struct Credentials {
@@ -67,19 +101,6 @@ pub async fn login(driver: &WebDriver) -> Result<()> {
     Ok(())
 }
 
-/// Load CSV data for tags.
-fn load_csv_data(path: &str) -> Result<HashMap<String, String>> {
-    let mut tags: HashMap<String, String> = HashMap::new();
-    let mut reader = Reader::from_path(path)?;
-
-    for line in reader.records() {
-        let record = line?;
-        tags.insert(record[0].to_string(), record[1].to_string());
-    }
-
-    Ok(tags)
-}
-
 /// Example function to add tags.
 async fn add_tags(clear: bool, driver: &WebDriver) -> Result<()> {
     let tags = load_csv_data(TAGPATH).unwrap();
@@ -147,10 +168,258 @@ async fn add_tags(clear: bool, driver: &WebDriver) -> Result<()> {
     Ok(())
 }
 
+/// Index the extracted files in `dir`, updating the index at `index_path` if it exists.
+fn index_directory(dir: &str, index_path: &str) -> Result<()> {
+    let index_path = PathBuf::from(index_path);
+    let mut model: Model = if index_path.exists() {
+        Model::load(&index_path)?
+    } else {
+        Model::new()
+    };
+
+    // Quoted phrases and NEAR/k need the word positions of a document
+    model.record_positions = true;
+    // Stored with the index for `tag:` filters and the tags metadata
+    model.set_tags(&load_csv_data(TAGPATH)?);
+    // The page tree gives each document its breadcrumb
+    let indexer = Indexer {
+        tree: Some(FileTree::from_json_file(TREEPATH).context("Could not load the page tree")?),
+        ..Indexer::default()
+    };
+    let report = indexer.index(&mut model, Path::new(dir))?;
+    println!(
+        "{} added, {} updated, {} removed, {} unchanged",
+        report.added.len(),
+        report.updated.len(),
+        report.removed.len(),
+        report.unchanged
+    );
+
+    // The tags may have changed even if no file did
+    model.save(&index_path, IndexFormat::Binary)?;
+    Ok(())
+}
+
+/// Questions in `model` without tags in `tags` by section, ordered by their
+/// section numbers so 1.1.2 comes before 1.1.10.
+fn untagged_questions<'m>(
+    model: &'m Model,
+    tags: &HashMap<String, String>,
+) -> Vec<(&'m str, &'m DocId)> {
+    let mut questions: Vec<(Vec<u32>, &str, &DocId)> = model
+        .docs
+        .keys()
+        .filter_map(|id| match id {
+            DocId::Question(question) => Some((question.section.as_str(), id)),
+            _ => None,
+        })
+        .filter(|(section, _)| {
+            tags.get(*section)
+                .is_none_or(|value| value.trim().is_empty())
+        })
+        .map(|(section, id)| {
+            let numbers = section.split('.').filter_map(|n| n.parse().ok()).collect();
+            (numbers, section, id)
+        })
+        .collect();
+    questions.sort();
+    questions.dedup_by(|a, b| a.1 == b.1);
+    questions
+        .into_iter()
+        .map(|(_, section, id)| (section, id))
+        .collect()
+}
+
+/// Print a draft `Key,Tags` CSV in the shape of `resources/tags.csv` with
+/// keywords of each question in the index that has no tags there yet.
+fn draft_tags(index_path: &str, method: KeywordMethod) -> Result<()> {
+    let model: Model = Model::load(index_path)?;
+    let tags = load_csv_data(TAGPATH)?;
+
+    let mut writer = csv::Writer::from_writer(io::stdout());
+    writer.write_record(["Key", "Tags"])?;
+    for (section, id) in untagged_questions(&model, &tags) {
+        let keywords: Vec<String> = model
+            .keywords(id, DRAFT_TAGS, method)
+            .into_iter()
+            .map(|keyword| keyword.surface)
+            .collect();
+        writer.write_record([section, keywords.join(", ").as_str()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Learn tags from the questions in the index that are tagged in
+/// `resources/tags.csv` and save the suggester to `suggester_path`.
+fn train_tags(index_path: &str, suggester_path: &str) -> Result<()> {
+    let model: Model = Model::load(index_path)?;
+    let tags = load_csv_data(TAGPATH)?;
+    let suggester = TagSuggester::train(&model, &tags);
+    if suggester.is_empty() {
+        bail!("no tagged questions in {}", index_path);
+    }
+    suggester.save(suggester_path, IndexFormat::Binary)?;
+    println!("learned from {} questions", suggester.len());
+    Ok(())
+}
+
+/// Print ranked tags with their confidence for the untagged questions.
+fn suggest_tags(index_path: &str, suggester_path: &str) -> Result<()> {
+    let model: Model = Model::load(index_path)?;
+    let suggester = TagSuggester::load(suggester_path)?;
+    let tags = load_csv_data(TAGPATH)?;
+
+    for (section, id) in untagged_questions(&model, &tags) {
+        let suggestions: Vec<String> = suggester
+            .suggest_for(&model, id, DRAFT_TAGS)
+            .into_iter()
+            .map(|suggestion| format!("{} ({:.2})", suggestion.tag, suggestion.confidence))
+            .collect();
+        println!("{}: {}", section, suggestions.join(", "));
+    }
+    Ok(())
+}
+
+/// Cross-validate `method` against the tags in `resources/tags.csv` and write
+/// the report to `report_path`, as Markdown if it ends in `.md`, else JSON.
+fn evaluate(index_path: &str, report_path: &str, method: &str, k: &str) -> Result<()> {
+    let method = match method {
+        "knn" => TagMethod::Suggester {
+            neighbours: DEFAULT_NEIGHBOURS,
+        },
+        "tfidf" => TagMethod::Keywords(KeywordMethod::TfIdf),
+        "textrank" => TagMethod::Keywords(KeywordMethod::TextRank),
+        _ => bail!("unknown method {:?}\n{}", method, USAGE),
+    };
+    let k = k
+        .parse()
+        .with_context(|| format!("invalid number of tags {:?}", k))?;
+
+    let model: Model = Model::load(index_path)?;
+    let tags = load_csv_data(TAGPATH)?;
+    let evaluation = Evaluation {
+        k,
+        ..Evaluation::default()
+    };
+    let report = evaluation.run(&model, &tags, method);
+
+    let content = if report_path.ends_with(".md") {
+        report.to_markdown()
+    } else {
+        serde_json::to_string_pretty(&report)?
+    };
+    fs::write(report_path, content)
+        .with_context(|| format!("could not write report {:?}", report_path))?;
+    println!(
+        "{}: P@{k} {:.3}, R@{k} {:.3}, F1 {:.3} over {} questions",
+        method.name(),
+        report.overall.precision,
+        report.overall.recall,
+        report.overall.f1,
+        report.overall.questions,
+        k = k,
+    );
+    Ok(())
+}
+
+/// Search the index interactively. The last word is completed from the index
+/// while typing; Tab accepts the first completion, Enter searches, Esc quits.
+fn complete_interactive(index_path: &str) -> Result<()> {
+    let model: Model = Model::load(index_path)?;
+    let completer = model.completer();
+
+    terminal::enable_raw_mode()?;
+    let result = read_queries(&model, &completer);
+    terminal::disable_raw_mode()?;
+    println!();
+    result
+}
+
+/// Input loop of [`complete_interactive`], run in raw terminal mode.
+fn read_queries(model: &Model, completer: &Completer) -> Result<()> {
+    let mut stdout = io::stdout();
+    let mut input = String::new();
+
+    loop {
+        let completions = completer.complete_query(&input, 5);
+        write!(
+            stdout,
+            "\r\x1b[2K> {}    \x1b[2m{}\x1b[0m\r\x1b[{}C",
+            input,
+            completions.join(" | "),
+            input.chars().count() + 2
+        )?;
+        stdout.flush()?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Tab => {
+                if let Some(completion) = completions.first() {
+                    input = format!("{} ", completion);
+                }
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                write!(stdout, "\r\x1b[2K> {}\r\n", input)?;
+                if let Some(suggestion) = model.did_you_mean(&input) {
+                    write!(stdout, "Did you mean: {}\r\n", suggestion)?;
+                }
+                for hit in model.search_hits(&input, 5) {
+                    let title = hit.metadata.get(meta::TITLE).map_or("", String::as_str);
+                    write!(stdout, "{:.4} {} {}\r\n", hit.score, hit.id, title)?;
+                    for snippet in &hit.snippets {
+                        write!(stdout, "    {}\r\n", snippet.render(&Highlight::Ansi))?;
+                    }
+                }
+                input.clear();
+            }
+            KeyCode::Char(ch) => input.push(ch),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let filetree = FileTree::from_json_file(PathBuf::from("resources/tree.json"))
-        .context("Could not create filetree from json")?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {}
+        ["split", ref pages @ ..] if !pages.is_empty() => {
+            for page in pages {
+                let written = split_page_file(Path::new(page))?;
+                println!("{}: {} questions", page, written.len());
+            }
+            return Ok(());
+        }
+        ["index", dir, index] => return index_directory(dir, index),
+        ["complete", index] => return complete_interactive(index),
+        ["draft-tags", index] | ["draft-tags", index, "tfidf"] => {
+            return draft_tags(index, KeywordMethod::TfIdf)
+        }
+        ["draft-tags", index, "textrank"] => return draft_tags(index, KeywordMethod::TextRank),
+        ["train-tags", index, suggester] => return train_tags(index, suggester),
+        ["suggest-tags", index, suggester] => return suggest_tags(index, suggester),
+        ["evaluate", index, report] => return evaluate(index, report, "knn", "5"),
+        ["evaluate", index, report, method] => return evaluate(index, report, method, "5"),
+        ["evaluate", index, report, method, k] => return evaluate(index, report, method, k),
+        _ => bail!("unknown command\n{}", USAGE),
+    }
+
+    let filetree =
+        FileTree::from_json_file(TREEPATH).context("Could not create filetree from json")?;
 
     let spider = Spider::new(DesiredCapabilities::firefox(), URL, filetree).await?;
 
@@ -1,111 +1,1224 @@
-use super::lexer::Lexer;
+use super::analyzer::{Analyzer, Filter, StandardAnalyzer};
+use super::complete::Completer;
+use super::compound::Decompounder;
+use super::document::{meta, DocId, Metadata};
+use super::fuzzy::{auto_distance, is_fuzzy_candidate, BkTree, FUZZY_WEIGHT};
+use super::keywords::{
+    is_keyword_candidate, is_noun_phrase, is_redundant, rank_phrases, text_rank, Keyword,
+    KeywordMethod,
+};
+use super::lexer::{is_section_number, Language, Normalization, Token, TokenKind};
+use super::query::{self, glob_match, Field, Query};
+use super::scorer::{Scorer, Scoring, TermStats};
+use super::snippet::{self, SearchHit, Snippet, SNIPPET_COUNT, SNIPPET_LEN};
+use super::tags::split_tags;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
 use std::time::SystemTime;
 
 type DocFreq = HashMap<String, usize>;
 type TermFreq = HashMap<String, usize>;
+type SurfaceForms = HashMap<String, HashMap<String, usize>>;
+/// Term → ascending word positions of the term in a document.
+type Positions = HashMap<String, Vec<u32>>;
+/// Tags of each question, keyed by its section number.
+type Tags = HashMap<String, Vec<String>>;
+/// Term weights of a text, scaled to unit length so that the dot product of
+/// two vectors is their cosine similarity.
+pub type TermVector = HashMap<String, f32>;
+/// Inverted index: term → documents containing it, with the term's frequency.
+type Postings = HashMap<String, HashMap<DocId, usize>>;
 
 #[derive(Deserialize, Serialize)]
 pub struct Doc {
     tf: TermFreq,
     count: usize,
     last_modified: SystemTime,
+    #[serde(default)]
+    language: Language,
+    /// Empty unless the model records positions.
+    #[serde(default)]
+    positions: Positions,
+    /// Source text, if the model stores it.
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    metadata: Metadata,
+    /// This document's share of `Model::surfaces`, subtracted on removal.
+    #[serde(default)]
+    surfaces: SurfaceForms,
 }
 
-type Docs = HashMap<PathBuf, Doc>;
+type Docs = HashMap<DocId, Doc>;
 
 #[derive(Default, Deserialize, Serialize)]
-pub struct Model {
+#[serde(from = "StoredModel<A>")]
+pub struct Model<A = StandardAnalyzer> {
     pub docs: Docs,
     pub df: DocFreq,
+    /// Language used for documents added without an explicit language.
+    #[serde(default)]
+    pub language: Language,
+    /// Analysis chain shared by indexing and querying.
+    #[serde(default)]
+    pub analyzer: A,
+    /// Original spellings seen for each term, with their occurrence counts.
+    #[serde(default)]
+    pub surfaces: SurfaceForms,
+    /// Question tags for `tag:` filters, see [`Model::set_tags`].
+    #[serde(default)]
+    pub tags: Tags,
+    /// Record word positions of added documents, enabling phrase and `NEAR/k`
+    /// queries against them.
+    #[serde(default)]
+    pub record_positions: bool,
+    /// Keep the text of added documents for snippets instead of re-reading
+    /// their files.
+    #[serde(default)]
+    pub store_text: bool,
+    /// Match query terms missing from the index against similar indexed terms,
+    /// as if they were written `term~`.
+    #[serde(default)]
+    pub fuzzy_fallback: bool,
+    /// Derived from `docs`; rebuilt by [`Model::rebuild_postings`] when
+    /// deserialized.
+    #[serde(skip)]
+    postings: Postings,
+    /// Sum of all document lengths, for the average document length.
+    #[serde(skip)]
+    total_count: usize,
+    /// Number of documents in each language, so queries are analyzed once per
+    /// language without visiting every document.
+    #[serde(skip)]
+    languages: HashMap<Language, usize>,
+    /// Indexed words for fuzzy matching, rebuilt with the postings.
+    #[serde(skip)]
+    fuzzy_terms: BkTree,
+}
+
+/// The serialized fields of a [`Model`], which is deserialized through this
+/// so that its derived fields are always rebuilt.
+#[derive(Deserialize)]
+struct StoredModel<A> {
+    docs: Docs,
+    df: DocFreq,
+    #[serde(default)]
+    language: Language,
+    #[serde(default)]
+    analyzer: A,
+    #[serde(default)]
+    surfaces: SurfaceForms,
+    #[serde(default)]
+    tags: Tags,
+    #[serde(default)]
+    record_positions: bool,
+    #[serde(default)]
+    store_text: bool,
+    #[serde(default)]
+    fuzzy_fallback: bool,
+}
+
+impl<A> From<StoredModel<A>> for Model<A> {
+    fn from(stored: StoredModel<A>) -> Self {
+        let mut model = Self {
+            docs: stored.docs,
+            df: stored.df,
+            language: stored.language,
+            analyzer: stored.analyzer,
+            surfaces: stored.surfaces,
+            tags: stored.tags,
+            record_positions: stored.record_positions,
+            store_text: stored.store_text,
+            fuzzy_fallback: stored.fuzzy_fallback,
+            postings: Postings::new(),
+            total_count: 0,
+            languages: HashMap::new(),
+            fuzzy_terms: BkTree::new(),
+        };
+        model.rebuild_postings();
+        model
+    }
+}
+
+/// A term of the index together with its readable form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VocabularyEntry<'m> {
+    pub term: &'m str,
+    /// Most frequent surface form, or the term itself if none was recorded.
+    pub surface: &'m str,
+    pub df: usize,
+}
+
+/// A search result ordered by score, ties broken by id.
+struct Hit<'m>(f32, &'m DocId);
+
+impl PartialEq for Hit<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Hit<'_> {}
+
+impl PartialOrd for Hit<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hit<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then_with(|| other.1.cmp(self.1))
+    }
+}
+
+/// Word positions of `tokens` in `text`, skipping shingles and n-grams.
+/// Tokens whose span starts inside the previous word, such as compound parts,
+/// synonyms or unit terms, share its position. Words and punctuation left out
+/// between two tokens, such as dropped stopwords, take up a position each, so
+/// phrases do not match across them.
+fn positioned<'t>(text: &str, tokens: &'t [Token]) -> Vec<(u32, &'t str)> {
+    let mut result = Vec::new();
+    let mut position = 0;
+    let mut word_end = None;
+
+    for token in tokens
+        .iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Shingle | TokenKind::NGram))
+    {
+        match word_end {
+            Some(end) if token.span.start < end => word_end = Some(end.max(token.span.end)),
+            Some(end) => {
+                let skipped = text
+                    .get(end..token.span.start)
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .filter(|gap| !gap.chars().all(|c| c == '-'))
+                    .count();
+                position += 1 + skipped as u32;
+                word_end = Some(token.span.end);
+            }
+            None => word_end = Some(token.span.end),
+        }
+        result.push((position, token.term.as_str()));
+    }
+
+    result
+}
+
+/// How the terms of a text query are looked up in the vocabulary.
+#[derive(Clone, Copy)]
+enum Matching {
+    Exact,
+    /// Fuzzy matching for terms missing from the index.
+    Fallback,
+    /// Fuzzy matching within the given or a length-dependent number of edits.
+    Fuzzy(Option<usize>),
+}
+
+/// Matching documents with their scores.
+type Scores<'m> = HashMap<&'m DocId, f32>;
+
+/// Documents in both `a` and `b`, with their scores added.
+fn intersect<'m>(a: Scores<'m>, b: Scores<'m>) -> Scores<'m> {
+    a.into_iter()
+        .filter_map(|(id, score)| Some((id, score + b.get(id)?)))
+        .collect()
+}
+
+/// Documents in `a` or `b`, with their scores added.
+fn union<'m>(mut a: Scores<'m>, b: Scores<'m>) -> Scores<'m> {
+    for (id, score) in b {
+        *a.entry(id).or_insert(0.0) += score;
+    }
+    a
+}
+
+/// Number of places where `terms` occur at their offsets from the first.
+fn phrase_count(positions: &Positions, terms: &[(u32, String)]) -> usize {
+    let Some(((_, first), rest)) = terms.split_first() else {
+        return 0;
+    };
+    let Some(starts) = positions.get(first) else {
+        return 0;
+    };
+
+    starts
+        .iter()
+        .filter(|&&start| {
+            rest.iter().all(|(offset, term)| {
+                positions
+                    .get(term)
+                    .is_some_and(|p| p.binary_search(&(start + offset)).is_ok())
+            })
+        })
+        .count()
+}
+
+/// Number of occurrences of `left` with `right` at most `distance` positions
+/// away in either direction.
+fn near_count(positions: &Positions, left: &str, right: &str, distance: u32) -> usize {
+    let (Some(lefts), Some(rights)) = (positions.get(left), positions.get(right)) else {
+        return 0;
+    };
+
+    lefts
+        .iter()
+        .filter(|&&l| {
+            rights
+                .iter()
+                .any(|&r| l.abs_diff(r) <= distance && (left != right || l != r))
+        })
+        .count()
+}
+
+impl<A> Model<A> {
+    /// Set the question tags used by `tag:` filters from the `Key,Tags` rows
+    /// returned by [`crate::tags::load_csv_data`].
+    pub fn set_tags(&mut self, tags: &HashMap<String, String>) {
+        self.tags = tags
+            .iter()
+            .map(|(key, value)| (key.clone(), split_tags(value)))
+            .collect();
+    }
+
+    /// Documents containing `term` with the term's frequency in each.
+    pub fn postings(&self, term: &str) -> impl Iterator<Item = (&DocId, usize)> {
+        self.postings
+            .get(term)
+            .into_iter()
+            .flatten()
+            .map(|(id, &tf)| (id, tf))
+    }
+
+    /// Metadata stored with the document `id`.
+    pub fn metadata(&self, id: &DocId) -> Option<&Metadata> {
+        self.docs.get(id).map(|doc| &doc.metadata)
+    }
+
+    /// Replace the metadata of the document `id`. Returns `false` if there is
+    /// no such document. Adding the document again clears its metadata.
+    pub fn set_metadata(&mut self, id: &DocId, metadata: Metadata) -> bool {
+        match self.docs.get_mut(id) {
+            Some(doc) => {
+                doc.metadata = metadata;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Rebuild the inverted index and the fuzzy term index from the
+    /// documents. Done when deserializing, since neither is stored.
+    pub fn rebuild_postings(&mut self) {
+        self.postings.clear();
+        self.total_count = 0;
+        self.languages.clear();
+        for (id, doc) in &self.docs {
+            for (term, &tf) in &doc.tf {
+                self.postings
+                    .entry(term.clone())
+                    .or_default()
+                    .insert(id.clone(), tf);
+            }
+            self.total_count += doc.count;
+            *self.languages.entry(doc.language).or_insert(0) += 1;
+        }
+
+        self.fuzzy_terms = BkTree::new();
+        for term in self.postings.keys().filter(|term| is_fuzzy_candidate(term)) {
+            self.fuzzy_terms.insert(term);
+        }
+    }
+
+    /// Indexed terms within `max_distance` edits of `term` with their distance,
+    /// closest first.
+    pub fn similar_terms(&self, term: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let mut similar = self.fuzzy_terms.find(term, max_distance);
+        // Terms of removed documents stay in the tree
+        similar.retain(|(term, _)| self.postings.contains_key(*term));
+        similar
+    }
 }
 
 impl Model {
-    /// Remove a document from the index, decrementing document frequencies.
-    fn remove_document(&mut self, path: &Path) {
-        if let Some(doc) = self.docs.remove(path) {
+    /// Creates an empty model with the default analyzer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable compound splitting with a dictionary built from the indexed
+    /// vocabulary. Documents indexed before this call keep their old terms
+    /// until they are added again.
+    pub fn enable_decompounding(&mut self, min_part_len: usize) {
+        let terms = self
+            .df
+            .iter()
+            .filter(|(_, &freq)| freq > 0)
+            .map(|(term, _)| term.clone());
+        let mut decompounder = Decompounder::from_terms(terms, min_part_len);
+        if let Some(Filter::Normalize(normalization)) = self
+            .analyzer
+            .filters
+            .iter()
+            .find(|filter| matches!(filter, Filter::Normalize(_)))
+        {
+            decompounder.normalization = *normalization;
+        }
+
+        self.analyzer
+            .filters
+            .retain(|filter| !matches!(filter, Filter::Decompound(_)));
+        self.analyzer.insert_after(
+            |filter| matches!(filter, Filter::Stopwords(_)),
+            Filter::Decompound(decompounder),
+        );
+    }
+}
+
+impl<A: Analyzer> Model<A> {
+    /// Creates an empty model analyzing text with `analyzer`.
+    pub fn with_analyzer(analyzer: A) -> Self {
+        Self {
+            docs: Docs::new(),
+            df: DocFreq::new(),
+            language: Language::default(),
+            analyzer,
+            surfaces: SurfaceForms::new(),
+            tags: Tags::new(),
+            record_positions: false,
+            store_text: false,
+            fuzzy_fallback: false,
+            postings: Postings::new(),
+            total_count: 0,
+            languages: HashMap::new(),
+            fuzzy_terms: BkTree::new(),
+        }
+    }
+
+    /// The most frequent original spelling of `term`, e.g. "Funktionsweise"
+    /// for the stem "funktionsweis".
+    pub fn surface_form(&self, term: &str) -> Option<&str> {
+        self.surface_forms(term, 1).first().map(|&(form, _)| form)
+    }
+
+    /// Up to `n` original spellings of `term` with their counts, most frequent
+    /// first.
+    pub fn surface_forms(&self, term: &str, n: usize) -> Vec<(&str, usize)> {
+        let mut forms: Vec<(&str, usize)> = self
+            .surfaces
+            .get(term)
+            .map(|forms| forms.iter().map(|(f, &c)| (f.as_str(), c)).collect())
+            .unwrap_or_default();
+        forms.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        forms.truncate(n);
+        forms
+    }
+
+    /// Readable form of `term`: its most frequent surface form or the term itself.
+    pub fn display_term<'m>(&'m self, term: &'m str) -> &'m str {
+        self.surface_form(term).unwrap_or(term)
+    }
+
+    /// All indexed terms with their surface forms, by descending document frequency.
+    pub fn vocabulary(&self) -> Vec<VocabularyEntry<'_>> {
+        let mut entries: Vec<VocabularyEntry> = self
+            .df
+            .iter()
+            .filter(|(_, &df)| df > 0)
+            .map(|(term, &df)| VocabularyEntry {
+                term,
+                surface: self.display_term(term),
+                df,
+            })
+            .collect();
+        entries.sort_by(|a, b| b.df.cmp(&a.df).then(a.term.cmp(b.term)));
+        entries
+    }
+
+    /// Prefix completion over the single-word terms of the index and their
+    /// surface forms. Build it once and reuse it while the user types.
+    pub fn completer(&self) -> Completer {
+        Completer::new(
+            self.df
+                .iter()
+                .filter(|(term, &df)| df > 0 && !term.contains(' '))
+                .map(|(term, &df)| (term.as_str(), df, self.surface_forms(term, usize::MAX))),
+        )
+    }
+
+    /// Remove a document from the index, decrementing document frequencies and
+    /// the counts of the spellings it contributed.
+    pub fn remove_document(&mut self, id: &DocId) {
+        if let Some(doc) = self.docs.remove(id) {
             for term in doc.tf.keys() {
                 if let Some(freq) = self.df.get_mut(term) {
                     *freq = freq.saturating_sub(1);
                 }
+                if let Some(postings) = self.postings.get_mut(term) {
+                    postings.remove(id);
+                    if postings.is_empty() {
+                        self.postings.remove(term);
+                    }
+                }
+            }
+            for (term, forms) in &doc.surfaces {
+                let Some(known) = self.surfaces.get_mut(term) else {
+                    continue;
+                };
+                for (form, n) in forms {
+                    if let Some(count) = known.get_mut(form) {
+                        *count = count.saturating_sub(*n);
+                        if *count == 0 {
+                            known.remove(form);
+                        }
+                    }
+                }
+                if known.is_empty() {
+                    self.surfaces.remove(term);
+                }
+            }
+            self.total_count = self.total_count.saturating_sub(doc.count);
+            if let Some(count) = self.languages.get_mut(&doc.language) {
+                *count -= 1;
+                if *count == 0 {
+                    self.languages.remove(&doc.language);
+                }
             }
         }
     }
 
-    /// Check whether the file at `path` needs re-indexing based on modification time.
-    pub fn requires_reindexing(&self, path: &Path, last_modified: SystemTime) -> bool {
+    /// Check whether the document `id` needs re-indexing based on the
+    /// modification time of its source.
+    pub fn requires_reindexing(&self, id: &DocId, last_modified: SystemTime) -> bool {
         self.docs
-            .get(path)
+            .get(id)
             .is_none_or(|doc| doc.last_modified < last_modified)
     }
 
-    /// Run a TF–IDF ranking over all indexed documents for the given query.
-    pub fn search_query(&self, query: &str) -> Vec<(PathBuf, f32)> {
-        // Tokenize and stem the query string
-        let tokens: Vec<String> = Lexer::new(query).collect();
+    /// Run a TF–IDF ranking over all documents matching the given query, written
+    /// in the syntax described at [`Query`].
+    pub fn search_query(&self, query: &str) -> Vec<(DocId, f32)> {
+        self.search_query_with(query, &Scoring::default())
+    }
 
-        // Compute TF–IDF score for each document
-        let mut results: Vec<(PathBuf, f32)> = self
-            .docs
+    /// Rank all documents matching the given query with `scorer`.
+    pub fn search_query_with<S: Scorer>(&self, query: &str, scorer: &S) -> Vec<(DocId, f32)> {
+        let mut results: Vec<(DocId, f32)> = self
+            .score_documents(query, scorer)
+            .into_iter()
+            .map(|(id, score)| (id.clone(), score))
+            .collect();
+
+        // Sort descending by score
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+
+    /// The `k` best documents for the given query, best first.
+    pub fn search_top_k(&self, query: &str, k: usize) -> Vec<(DocId, f32)> {
+        self.search_top_k_with(query, k, &Scoring::default())
+    }
+
+    /// The `k` best documents for the given query ranked with `scorer`, best
+    /// first. Keeps a heap of at most `k` hits instead of sorting all results.
+    pub fn search_top_k_with<S: Scorer>(
+        &self,
+        query: &str,
+        k: usize,
+        scorer: &S,
+    ) -> Vec<(DocId, f32)> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        // Min-heap of the best hits so far; its top is the worst of them
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (id, score) in self.score_documents(query, scorer) {
+            heap.push(Reverse(Hit(score, id)));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(Hit(score, id))| (id.clone(), score))
+            .collect()
+    }
+
+    /// The `k` best documents for the given query with their best matching
+    /// passages, best first.
+    pub fn search_hits(&self, query: &str, k: usize) -> Vec<SearchHit> {
+        self.search_hits_with(query, k, &Scoring::default())
+    }
+
+    /// Like [`Model::search_hits`], ranking with `scorer`.
+    pub fn search_hits_with<S: Scorer>(&self, query: &str, k: usize, scorer: &S) -> Vec<SearchHit> {
+        self.search_top_k_with(query, k, scorer)
+            .into_iter()
+            .map(|(id, score)| SearchHit {
+                snippets: self.snippets(&id, query, SNIPPET_COUNT),
+                metadata: self.metadata(&id).cloned().unwrap_or_default(),
+                id,
+                score,
+            })
+            .collect()
+    }
+
+    /// The query with each word missing from the index replaced by the most
+    /// common similar indexed term, or `None` if no word could be corrected.
+    pub fn did_you_mean(&self, query: &str) -> Option<String> {
+        let mut corrections: Vec<(Range<usize>, &str)> = Vec::new();
+        for span in query::text_spans(query) {
+            let mut word_end = 0;
+            for token in self.analyzer.analyze(&query[span.clone()], self.language) {
+                // Parts of compounds lie within the compound itself
+                if token.kind != TokenKind::Word || token.span.start < word_end {
+                    continue;
+                }
+                word_end = token.span.end;
+                if self.postings.contains_key(&token.term) || !is_fuzzy_candidate(&token.term) {
+                    continue;
+                }
+
+                let best = self
+                    .similar_terms(&token.term, auto_distance(&token.term).max(1))
+                    .into_iter()
+                    .min_by_key(|&(term, edits)| (edits, Reverse(self.df[term]), term));
+                if let Some((term, _)) = best {
+                    let start = span.start + token.span.start;
+                    let end = span.start + token.span.end;
+                    corrections.push((start..end, self.display_term(term)));
+                }
+            }
+        }
+
+        if corrections.is_empty() {
+            return None;
+        }
+        let mut corrected = query.to_string();
+        for (range, replacement) in corrections.into_iter().rev() {
+            corrected.replace_range(range, replacement);
+        }
+        Some(corrected)
+    }
+
+    /// Up to `count` passages of the document `id` matching `query`, with the
+    /// matched terms highlighted. Uses the stored text, or re-reads the file
+    /// the document came from if the model does not store text.
+    pub fn snippets(&self, id: &DocId, query: &str, count: usize) -> Vec<Snippet> {
+        let Some(doc) = self.docs.get(id) else {
+            return Vec::new();
+        };
+        let Some(text) = self.doc_text(id, doc) else {
+            return Vec::new();
+        };
+
+        let query = query::parse(query);
+        let terms: HashSet<String> = query
+            .positive_text()
+            .into_iter()
+            .flat_map(|text| self.analyzer.terms(text, doc.language))
+            .collect();
+        let tokens = self.analyzer.analyze(&text, doc.language);
+        snippet::snippets(&text, &tokens, &terms, count, SNIPPET_LEN)
+    }
+
+    /// Text of `doc`: the stored text, or the file it was read from.
+    fn doc_text<'d>(&self, id: &'d DocId, doc: &'d Doc) -> Option<Cow<'d, str>> {
+        if let Some(text) = &doc.text {
+            return Some(Cow::Borrowed(text.as_str()));
+        }
+        let path = match (doc.metadata.get(meta::PATH), id) {
+            (Some(path), _) => Path::new(path),
+            (None, DocId::File(path)) => path.as_path(),
+            (None, _) => return None,
+        };
+        fs::read_to_string(path).ok().map(Cow::Owned)
+    }
+
+    /// Up to `k` distinctive terms and phrases of the document `id`, best
+    /// first, with their readable surface forms. Keywords contained in a
+    /// better one are skipped. [`KeywordMethod::TextRank`] needs the document
+    /// text and falls back to TF-IDF if it is neither stored nor readable.
+    pub fn keywords(&self, id: &DocId, k: usize, method: KeywordMethod) -> Vec<Keyword> {
+        let Some(doc) = self.docs.get(id) else {
+            return Vec::new();
+        };
+        let text = match method {
+            KeywordMethod::TfIdf => None,
+            KeywordMethod::TextRank => self.doc_text(id, doc),
+        };
+
+        let mut candidates: Vec<Keyword> = match &text {
+            Some(text) => self.text_rank_candidates(text, doc.language),
+            None => doc
+                .tf
+                .iter()
+                .filter(|(term, _)| is_keyword_candidate(term))
+                .map(|(term, &tf)| Keyword {
+                    term: term.clone(),
+                    surface: self.display_term(term).to_string(),
+                    score: self.score(&Scoring::TfIdf, doc, tf, self.df[term]),
+                })
+                .collect(),
+        };
+        candidates.retain(|keyword| {
+            is_noun_phrase(&keyword.surface, doc.language)
+                && self.keeps_every_word(&keyword.surface, doc.language)
+        });
+        candidates.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.term.cmp(&b.term))
+        });
+
+        let mut keywords: Vec<Keyword> = Vec::new();
+        for candidate in candidates {
+            if keywords.len() == k {
+                break;
+            }
+            if !keywords
+                .iter()
+                .any(|kw| is_redundant(&kw.term, &candidate.term))
+            {
+                keywords.push(candidate);
+            }
+        }
+        keywords
+    }
+
+    /// Whether analyzing `surface` keeps a term for each of its words, so a
+    /// phrase with a stopword inside, such as `Transport der Ladung`, is not
+    /// offered as a keyword.
+    fn keeps_every_word(&self, surface: &str, language: Language) -> bool {
+        let tokens = self.analyzer.analyze(surface, language);
+        let mut positions: Vec<u32> = positioned(surface, &tokens)
+            .into_iter()
+            .map(|(position, _)| position)
+            .collect();
+        positions.dedup();
+        positions.windows(2).all(|pair| pair[1] == pair[0] + 1)
+            && positions.len() >= surface.split_whitespace().count()
+    }
+
+    /// TF-IDF vector of the document `id`, weighted like [`Model::text_vector`].
+    pub fn term_vector(&self, id: &DocId) -> Option<TermVector> {
+        self.docs.get(id).map(|doc| self.weigh(&doc.tf))
+    }
+
+    /// TF-IDF vector of `text` analyzed in the model's language, each term
+    /// weighted by [`Scoring::LogTfIdf`] over the indexed documents.
+    pub fn text_vector(&self, text: &str) -> TermVector {
+        let mut tf = TermFreq::new();
+        for term in self.analyzer.terms(text, self.language) {
+            *tf.entry(term).or_insert(0) += 1;
+        }
+        self.weigh(&tf)
+    }
+
+    fn weigh(&self, tf: &TermFreq) -> TermVector {
+        let doc_len = tf.values().sum();
+        let mut vector: TermVector = tf
+            .iter()
+            .map(|(term, &tf)| {
+                let stats = TermStats {
+                    tf,
+                    doc_len,
+                    avg_doc_len: doc_len as f32,
+                    df: self.df.get(term).copied().unwrap_or(0),
+                    doc_count: self.docs.len(),
+                };
+                (term.clone(), Scoring::LogTfIdf.score(&stats))
+            })
+            .collect();
+
+        let norm = vector.values().map(|w| w * w).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.values_mut().for_each(|w| *w /= norm);
+        }
+        vector
+    }
+
+    /// Phrases of `text` scored by the TextRank of their words times their
+    /// IDF in the index.
+    fn text_rank_candidates(&self, text: &str, language: Language) -> Vec<Keyword> {
+        let tokens = self.analyzer.analyze(text, language);
+
+        // Runs of adjacent content words; anything else between two words,
+        // such as punctuation or a dropped stopword, ends a run
+        let mut runs: Vec<Vec<&Token>> = vec![Vec::new()];
+        let mut word_end = 0;
+        for token in &tokens {
+            if token.span.start < word_end {
+                // Compound parts and synonyms of the previous word
+                continue;
+            }
+            let adjacent = text[word_end..token.span.start].trim().is_empty();
+            let current = runs.last_mut().expect("runs is never empty");
+            if !adjacent && !current.is_empty() {
+                runs.push(Vec::new());
+            }
+            word_end = token.span.end;
+            if token.kind == TokenKind::Word && is_keyword_candidate(&token.term) {
+                runs.last_mut().expect("runs is never empty").push(token);
+            } else if !runs.last().expect("runs is never empty").is_empty() {
+                runs.push(Vec::new());
+            }
+        }
+
+        let terms: Vec<Vec<&str>> = runs
             .iter()
-            .filter_map(|(path, doc)| {
-                let score: f32 = tokens
-                    .iter()
-                    .map(|t| compute_tf(t, doc) * compute_idf(t, self.docs.len(), &self.df))
-                    .sum();
-
-                if score.is_finite() && score > 0.0 {
-                    Some((path.clone(), score))
+            .map(|run| run.iter().map(|token| token.term.as_str()).collect())
+            .collect();
+        let ranks = text_rank(&terms);
+
+        let doc_count = self.docs.len() as f32;
+        let mut best: HashMap<String, Keyword> = HashMap::new();
+        for (run, words, rank) in rank_phrases(&terms, &ranks) {
+            let term = terms[run][words.clone()].join(" ");
+            let df = self.df.get(&term).copied().unwrap_or(1).max(1) as f32;
+            let score = rank * (1.0 + doc_count / df).ln();
+            if best.get(&term).is_some_and(|kw| kw.score >= score) {
+                continue;
+            }
+            let surface = match self.surface_form(&term) {
+                Some(surface) => surface.to_string(),
+                None => {
+                    let span = runs[run][words.start].span.start..runs[run][words.end - 1].span.end;
+                    text[span].split_whitespace().collect::<Vec<_>>().join(" ")
+                }
+            };
+            best.insert(
+                term.clone(),
+                Keyword {
+                    term,
+                    surface,
+                    score,
+                },
+            );
+        }
+        best.into_values().collect()
+    }
+
+    /// Evaluate `query` into the matching documents and their scores. Only the
+    /// postings of query terms are visited, not every document.
+    fn score_documents<S: Scorer>(&self, query: &str, scorer: &S) -> Scores<'_> {
+        let languages: HashSet<Language> = self.languages.keys().copied().collect();
+        let mut scores = self.evaluate(&query::parse(query), scorer, &languages);
+        scores.retain(|_, score| score.is_finite());
+        scores
+    }
+
+    /// Score of a single term or clause occurring `tf` times in `doc`.
+    fn score<S: Scorer>(&self, scorer: &S, doc: &Doc, tf: usize, df: usize) -> f32 {
+        let doc_count = self.docs.len();
+        scorer.score(&TermStats {
+            tf,
+            doc_len: doc.count,
+            avg_doc_len: self.total_count as f32 / doc_count.max(1) as f32,
+            df,
+            doc_count,
+        })
+    }
+
+    /// Matching documents of a parsed query with their scores.
+    fn evaluate<S: Scorer>(
+        &self,
+        query: &Query,
+        scorer: &S,
+        languages: &HashSet<Language>,
+    ) -> Scores<'_> {
+        let evaluate = |query: &Query| self.evaluate(query, scorer, languages);
+
+        match query {
+            Query::Text(text) => {
+                let matching = if self.fuzzy_fallback {
+                    Matching::Fallback
                 } else {
-                    None
+                    Matching::Exact
+                };
+                self.text_scores(text, matching, scorer, languages)
+            }
+            Query::Fuzzy { text, distance } => {
+                self.text_scores(text, Matching::Fuzzy(*distance), scorer, languages)
+            }
+            Query::Phrase(_) | Query::Near { .. } => {
+                // Scored like a single term occurring once per match
+                let matches = self.clause_matches(query, languages);
+                let df = matches.len();
+                matches
+                    .into_iter()
+                    .map(|(id, tf)| (id, self.score(scorer, &self.docs[id], tf, df)))
+                    .collect()
+            }
+            Query::Filter { field, pattern } => self
+                .filter_matches(field, pattern)
+                .into_iter()
+                .map(|id| (id, 0.0))
+                .collect(),
+            Query::And(queries) => queries
+                .iter()
+                .map(evaluate)
+                .reduce(intersect)
+                .unwrap_or_default(),
+            Query::Or(queries) => queries.iter().map(evaluate).fold(Scores::new(), union),
+            Query::Bool {
+                should,
+                must,
+                must_not,
+            } => {
+                let optional = should.iter().map(evaluate).fold(Scores::new(), union);
+                let mut scores = match must.iter().map(evaluate).reduce(intersect) {
+                    // Optional clauses only add to the score of required matches
+                    Some(required) => required
+                        .into_iter()
+                        .map(|(id, score)| (id, score + optional.get(id).copied().unwrap_or(0.0)))
+                        .collect(),
+                    // Only exclusions: start from every document
+                    None if should.is_empty() && !must_not.is_empty() => {
+                        self.docs.keys().map(|id| (id, 0.0)).collect()
+                    }
+                    None => optional,
+                };
+                for query in must_not {
+                    for id in evaluate(query).keys() {
+                        scores.remove(id);
+                    }
                 }
-            })
+                scores
+            }
+        }
+    }
+
+    /// Scores of the terms of `text`. Similar terms found by fuzzy matching
+    /// score lower per edit.
+    fn text_scores<S: Scorer>(
+        &self,
+        text: &str,
+        matching: Matching,
+        scorer: &S,
+        languages: &HashSet<Language>,
+    ) -> Scores<'_> {
+        // Tokenize and stem the text once per language present in the index
+        let mut scores = Scores::new();
+        for &language in languages {
+            for t in self.analyzer.terms(text, language) {
+                let expansions = match matching {
+                    Matching::Fuzzy(distance) if is_fuzzy_candidate(&t) => {
+                        let distance = distance.unwrap_or_else(|| auto_distance(&t).max(1));
+                        self.similar_terms(&t, distance)
+                    }
+                    Matching::Fallback if !self.postings.contains_key(&t) => {
+                        self.similar_terms(&t, auto_distance(&t))
+                    }
+                    _ => vec![(t.as_str(), 0)],
+                };
+
+                for (term, edits) in expansions {
+                    let df = *self.df.get(term).unwrap_or(&1);
+                    let weight = FUZZY_WEIGHT.powi(edits as i32);
+                    for (id, tf) in self.postings(term) {
+                        let doc = &self.docs[id];
+                        if doc.language == language {
+                            *scores.entry(id).or_insert(0.0) +=
+                                weight * self.score(scorer, doc, tf, df);
+                        }
+                    }
+                }
+            }
+        }
+        scores
+    }
+
+    /// Documents matching a field filter. `section:` and `tag:` use the
+    /// section and tags metadata of a document; documents without it match
+    /// if they mention a matching section number, or the section number of a
+    /// question with a matching tag. Other fields match the metadata of the
+    /// same name, or any element of a comma separated value.
+    fn filter_matches(&self, field: &Field, pattern: &str) -> HashSet<&DocId> {
+        let fold = |value: &str| Normalization::default().word(&value.to_lowercase());
+        let pattern = fold(pattern);
+        let matches = |value: &str| {
+            glob_match(&pattern, &fold(value))
+                || value
+                    .split(',')
+                    .any(|element| glob_match(&pattern, &fold(element.trim())))
+        };
+
+        let key = match field {
+            Field::Section => meta::SECTION,
+            Field::Tag => meta::TAGS,
+            Field::Meta(key) => key.as_str(),
+        };
+        let mut found: HashSet<&DocId> = self
+            .docs
+            .iter()
+            .filter(|(_, doc)| doc.metadata.get(key).is_some_and(|value| matches(value)))
+            .map(|(id, _)| id)
             .collect();
 
-        // Sort descending by score
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        results
+        let sections: Vec<&str> = match field {
+            Field::Section => self
+                .postings
+                .keys()
+                .filter(|term| is_section_number(term) && glob_match(&pattern, term))
+                .map(String::as_str)
+                .collect(),
+            Field::Tag => self
+                .tags
+                .iter()
+                .filter(|(_, tags)| tags.iter().any(|tag| glob_match(&pattern, &fold(tag))))
+                .map(|(section, _)| section.as_str())
+                .collect(),
+            Field::Meta(_) => Vec::new(),
+        };
+        found.extend(
+            sections
+                .into_iter()
+                .flat_map(|section| self.postings(section).map(|(id, _)| id))
+                .filter(|id| !self.docs[*id].metadata.contains_key(key)),
+        );
+        found
+    }
+
+    /// Documents satisfying a phrase or `NEAR/k` clause, with the number of
+    /// matches in each. Documents without recorded positions match a phrase by
+    /// the shingle term indexed for it; they never match `NEAR/k`.
+    fn clause_matches(
+        &self,
+        clause: &Query,
+        languages: &HashSet<Language>,
+    ) -> Vec<(&DocId, usize)> {
+        let mut matches = Vec::new();
+
+        for &language in languages {
+            let terms = match clause {
+                Query::Phrase(phrase) => self.phrase_terms(phrase, language),
+                Query::Near { left, right, .. } => {
+                    let left = self.phrase_terms(left, language);
+                    let right = self.phrase_terms(right, language);
+                    match (left.into_iter().next(), right.into_iter().next()) {
+                        (Some(left), Some(right)) => vec![left, right],
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+            let Some((_, first)) = terms.first() else {
+                continue;
+            };
+            let shingle = match clause {
+                Query::Phrase(phrase) if terms.len() > 1 => self.phrase_shingle(phrase, language),
+                _ => None,
+            };
+
+            for (id, tf) in self.postings(first) {
+                let doc = &self.docs[id];
+                if doc.language != language {
+                    continue;
+                }
+                let count = match clause {
+                    Query::Near { distance, .. } => {
+                        near_count(&doc.positions, &terms[0].1, &terms[1].1, *distance)
+                    }
+                    _ if !doc.positions.is_empty() => phrase_count(&doc.positions, &terms),
+                    _ if terms.len() == 1 => tf,
+                    _ => shingle
+                        .as_ref()
+                        .and_then(|shingle| doc.tf.get(shingle))
+                        .copied()
+                        .unwrap_or(0),
+                };
+                if count > 0 {
+                    matches.push((id, count));
+                }
+            }
+        }
+
+        matches
+    }
+
+    /// Terms of `phrase` by word position, the first term of each position
+    /// with its offset from the first word.
+    fn phrase_terms(&self, phrase: &str, language: Language) -> Vec<(u32, String)> {
+        let tokens = self.analyzer.analyze(phrase, language);
+        let mut terms: Vec<(u32, String)> = Vec::new();
+        for (position, term) in positioned(phrase, &tokens) {
+            if terms.last().is_none_or(|(last, _)| *last != position) {
+                terms.push((position, term.to_string()));
+            }
+        }
+        terms
+    }
+
+    /// Shingle term of `phrase` spanning all of its words, if the analyzer
+    /// produces one.
+    fn phrase_shingle(&self, phrase: &str, language: Language) -> Option<String> {
+        let tokens = self.analyzer.analyze(phrase, language);
+        let words = tokens.iter().filter(|t| t.kind != TokenKind::Shingle);
+        let start = words.clone().map(|t| t.span.start).min()?;
+        let end = words.map(|t| t.span.end).max()?;
+        tokens
+            .into_iter()
+            .find(|t| t.kind == TokenKind::Shingle && t.span == (start..end))
+            .map(|t| t.term)
+    }
+
+    /// Add or update a document in the index using the model's default language.
+    pub fn add_document<I: Into<DocId>>(
+        &mut self,
+        id: I,
+        last_modified: SystemTime,
+        content: &str,
+    ) {
+        self.add_document_with_language(id, last_modified, content, self.language);
     }
 
     /// Add or update a document in the index, updating TF and DF maps.
-    pub fn add_document(&mut self, path: PathBuf, last_modified: SystemTime, content: &str) {
+    /// The language is remembered so queries against it are stemmed the same way.
+    pub fn add_document_with_language<I: Into<DocId>>(
+        &mut self,
+        id: I,
+        last_modified: SystemTime,
+        content: &str,
+        language: Language,
+    ) {
         // Remove existing entry (decrements df)
-        self.remove_document(&path);
+        let id = id.into();
+        self.remove_document(&id);
 
-        // Build term frequencies
-        let tf: TermFreq = Lexer::new(content).fold(HashMap::new(), |mut acc, token| {
-            *acc.entry(token).or_insert(0) += 1;
-            acc
-        });
+        // Build term frequencies, remembering how each term was spelled
+        let tokens = self.analyzer.analyze(content, language);
+        let mut positions = Positions::new();
+        if self.record_positions {
+            for (position, term) in positioned(content, &tokens) {
+                let list = positions.entry(term.to_string()).or_default();
+                if list.last() != Some(&position) {
+                    list.push(position);
+                }
+            }
+        }
+
+        let mut tf = TermFreq::new();
+        let mut surfaces = SurfaceForms::new();
+        for token in tokens {
+            let surface = token.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            *surfaces
+                .entry(token.term.clone())
+                .or_default()
+                .entry(surface)
+                .or_insert(0) += 1;
+            *tf.entry(token.term).or_insert(0) += 1;
+        }
+        for (term, forms) in &surfaces {
+            let known = self.surfaces.entry(term.clone()).or_default();
+            for (form, &n) in forms {
+                *known.entry(form.clone()).or_insert(0) += n;
+            }
+        }
         let count: usize = tf.values().sum();
 
-        // Update document frequencies
-        for term in tf.keys() {
+        // Update document frequencies and postings
+        for (term, &freq) in &tf {
+            if !self.postings.contains_key(term) && is_fuzzy_candidate(term) {
+                self.fuzzy_terms.insert(term);
+            }
             *self.df.entry(term.clone()).or_insert(0) += 1;
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(id.clone(), freq);
         }
+        self.total_count += count;
+        *self.languages.entry(language).or_insert(0) += 1;
 
         // Insert new document record
         let doc = Doc {
             tf,
             count,
             last_modified,
+            language,
+            positions,
+            text: self.store_text.then(|| content.to_string()),
+            metadata: Metadata::new(),
+            surfaces,
         };
-        self.docs.insert(path, doc);
+        self.docs.insert(id, doc);
     }
 }
 
-/// Term frequency: term count divided by total terms in document.
-fn compute_tf(term: &str, doc: &Doc) -> f32 {
-    let total = doc.count as f32;
-    let freq = *doc.tf.get(term).unwrap_or(&0) as f32;
-    freq / total
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phrase_does_not_match_across_sentences() {
+        let mut model = Model::new();
+        model.record_positions = true;
+        model.add_document(
+            DocId::Node("regelung".to_string()),
+            SystemTime::UNIX_EPOCH,
+            "Der Fehler ist bleibend. Regelabweichung tritt nicht auf.",
+        );
+
+        assert!(model
+            .search_query("\"bleibend Regelabweichung\"")
+            .is_empty());
+        assert_eq!(model.search_query("\"Fehler ist bleibend\"").len(), 1);
+    }
+
+    #[test]
+    fn deserializing_rebuilds_postings() {
+        let mut model = Model::new();
+        model.add_document(
+            DocId::Node("strom".to_string()),
+            SystemTime::UNIX_EPOCH,
+            "Der elektrische Strom fließt durch den Leiter.",
+        );
 
-/// Inverse document frequency: log10(N / df), with df at least 1.
-fn compute_idf(term: &str, doc_count: usize, df: &DocFreq) -> f32 {
-    let n = doc_count as f32;
-    let m = *df.get(term).unwrap_or(&1) as f32;
-    (n / m).log10()
+        let json = serde_json::to_string(&model).unwrap();
+        let mut model: Model = serde_json::from_str(&json).unwrap();
+        assert_eq!(model.search_query("Leiter").len(), 1);
+        model.remove_document(&DocId::Node("strom".to_string()));
+        assert!(model.search_query("Leiter").is_empty());
+    }
+
+    #[test]
+    fn keywords_are_noun_phrases() {
+        let mut model = Model::new();
+        model.store_text = true;
+        let page = include_str!("../resources/extracted-treeitem-c6643bf0-label.txt");
+        for question in crate::question::split_questions(page) {
+            model.add_document(
+                DocId::Node(question.section),
+                SystemTime::UNIX_EPOCH,
+                &question.text,
+            );
+        }
+
+        let id = DocId::Node("1.1.1".to_string());
+        for method in [KeywordMethod::TfIdf, KeywordMethod::TextRank] {
+            let keywords = model.keywords(&id, 10, method);
+            assert!(!keywords.is_empty());
+            for keyword in keywords {
+                assert!(
+                    ![
+                        "außerdem ein Transportmedium",
+                        "besonders Metalle",
+                        "Stromkreises meist Elektronen",
+                    ]
+                    .contains(&keyword.surface.as_str()),
+                    "{:?} is not a noun phrase",
+                    keyword.surface
+                );
+            }
+        }
+    }
 }
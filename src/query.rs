@@ -0,0 +1,437 @@
+use std::ops::Range;
+
+/// Fields that can be filtered on with `field:pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// Section number of a question, e.g. `section:1.1.*`.
+    Section,
+    /// Tags of a question, e.g. `tag:Widerstand`.
+    Tag,
+    /// Any other document metadata, e.g. `title:Kapazität*`.
+    Meta(String),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "section" => Field::Section,
+            "tag" | "tags" => Field::Tag,
+            _ => Field::Meta(name.to_string()),
+        }
+    }
+}
+
+/// A parsed search query.
+///
+/// Words written next to each other are optional and their scores add up, as
+/// in a plain bag-of-words query. `+word` and `-word` require or exclude a
+/// word, `NOT` excludes the clause after it, and `AND`/`OR` combine clauses;
+/// `AND` binds tighter than `OR`. Clauses can be grouped with parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Free text, scored term by term; matches documents with any of its terms.
+    Text(String),
+    /// Words that must appear consecutively, written in double quotes.
+    Phrase(String),
+    /// Two words at most `distance` positions apart in either order, written
+    /// `Spannung NEAR/3 Quelle`.
+    Near {
+        left: String,
+        right: String,
+        distance: u32,
+    },
+    /// A word matched against similar indexed terms, written `Wiederstand~` or
+    /// with the number of tolerated edits, `Wiederstand~2`.
+    Fuzzy {
+        text: String,
+        distance: Option<usize>,
+    },
+    /// A field filter; `*` and `?` in the pattern are wildcards.
+    Filter { field: Field, pattern: String },
+    /// Documents matching all of the queries.
+    And(Vec<Query>),
+    /// Documents matching any of the queries.
+    Or(Vec<Query>),
+    /// Documents matching all `must` queries, or any `should` query if there is
+    /// no `must`, and none of the `must_not` queries.
+    Bool {
+        should: Vec<Query>,
+        must: Vec<Query>,
+        must_not: Vec<Query>,
+    },
+}
+
+/// A lexical unit of the raw query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lexeme<'a> {
+    Open,
+    Close,
+    Plus,
+    Minus,
+    Word(&'a str),
+    Phrase(&'a str),
+    /// Name of a field, followed by its value.
+    Field(&'a str),
+}
+
+/// Whether `ch` ends a word of the query.
+fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || matches!(ch, '"' | '(' | ')')
+}
+
+/// Whether `name` can name a field: lowercase ASCII letters and underscores.
+fn is_field_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+}
+
+/// Split `query` into lexemes. An unterminated quote runs to the end of the
+/// query.
+fn lex(query: &str) -> Vec<Lexeme<'_>> {
+    let mut lexemes = Vec::new();
+    let mut rest = query;
+
+    loop {
+        rest = rest.trim_start();
+        let Some(ch) = rest.chars().next() else {
+            break;
+        };
+
+        match ch {
+            '(' => lexemes.push(Lexeme::Open),
+            ')' => lexemes.push(Lexeme::Close),
+            '"' => {
+                let quoted = &rest[1..];
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                if !quoted[..end].trim().is_empty() {
+                    lexemes.push(Lexeme::Phrase(quoted[..end].trim()));
+                }
+                rest = quoted.get(end + 1..).unwrap_or_default();
+                continue;
+            }
+            '+' | '-' if rest[1..].starts_with(|c: char| !c.is_whitespace()) => {
+                lexemes.push(if ch == '+' {
+                    Lexeme::Plus
+                } else {
+                    Lexeme::Minus
+                });
+            }
+            _ => {
+                let end = rest.find(is_delimiter).unwrap_or(rest.len());
+                let word = &rest[..end];
+                let field = word.split_once(':').filter(|(name, value)| {
+                    is_field_name(name) && (!value.is_empty() || rest[end..].starts_with('"'))
+                });
+                if let Some((name, _)) = field {
+                    // The value is lexed on its own, so it may be a phrase
+                    lexemes.push(Lexeme::Field(name));
+                    rest = &rest[name.len() + 1..];
+                } else {
+                    lexemes.push(Lexeme::Word(word));
+                    rest = &rest[end..];
+                }
+                continue;
+            }
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    lexemes
+}
+
+/// Distance of a `NEAR/k` operator.
+fn near_distance(word: &str) -> Option<u32> {
+    word.strip_prefix("NEAR/")?.parse().ok()
+}
+
+/// Split a `word~` or `word~N` into the word and the optional edit distance.
+fn fuzzy_word(word: &str) -> Option<(&str, Option<usize>)> {
+    let (text, distance) = word.rsplit_once('~')?;
+    if text.is_empty() {
+        return None;
+    }
+    match distance {
+        "" => Some((text, None)),
+        _ => Some((text, Some(distance.parse().ok()?))),
+    }
+}
+
+/// Byte ranges of the searchable text in `query`: plain, fuzzy and quoted
+/// words, leaving out operators, field filters and syntax characters.
+pub fn text_spans(query: &str) -> Vec<Range<usize>> {
+    // Lexemes borrow from `query`, so their offset follows from the pointers
+    let span = |part: &str| {
+        let start = part.as_ptr() as usize - query.as_ptr() as usize;
+        start..start + part.len()
+    };
+
+    let lexemes = lex(query);
+    let mut spans = Vec::new();
+    let mut after_field = false;
+    for lexeme in lexemes {
+        match lexeme {
+            Lexeme::Word("AND" | "OR" | "NOT") => {}
+            Lexeme::Word(word) | Lexeme::Phrase(word)
+                if !after_field && near_distance(word).is_none() =>
+            {
+                let text = fuzzy_word(word).map_or(word, |(text, _)| text);
+                spans.push(span(text));
+            }
+            _ => {}
+        }
+        after_field = matches!(lexeme, Lexeme::Field(_));
+    }
+
+    spans
+}
+
+/// How a clause of a [`Query::Bool`] takes part in matching.
+enum Occur {
+    Should,
+    Must,
+    MustNot,
+}
+
+/// Recursive descent parser over the lexemes of a query.
+struct Parser<'a> {
+    lexemes: Vec<Lexeme<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Lexeme<'a>> {
+        self.lexemes.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Lexeme<'a>> {
+        let lexeme = self.peek()?;
+        self.pos += 1;
+        Some(lexeme)
+    }
+
+    /// Consumes the keyword `keyword` if it comes next.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek() == Some(Lexeme::Word(keyword));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// `or := and ("OR" and)*`
+    fn or(&mut self) -> Query {
+        let mut queries = vec![self.and()];
+        while self.keyword("OR") {
+            queries.push(self.and());
+        }
+        simplify(queries, Query::Or)
+    }
+
+    /// `and := sequence ("AND" sequence)*`
+    fn and(&mut self) -> Query {
+        let mut queries = vec![self.sequence()];
+        while self.keyword("AND") {
+            queries.push(self.sequence());
+        }
+        simplify(queries, Query::And)
+    }
+
+    /// A run of clauses without operators between them.
+    fn sequence(&mut self) -> Query {
+        let mut should = Vec::new();
+        let mut must = Vec::new();
+        let mut must_not = Vec::new();
+        // Adjacent plain words, kept together so shingles still form
+        let mut words: Vec<String> = Vec::new();
+
+        loop {
+            match self.peek() {
+                None | Some(Lexeme::Close | Lexeme::Word("AND" | "OR")) => break,
+                _ => {}
+            }
+
+            let (occur, query) = match self.next() {
+                Some(Lexeme::Plus) => (Occur::Must, self.primary()),
+                Some(Lexeme::Minus) => (Occur::MustNot, self.primary()),
+                Some(Lexeme::Word("NOT")) => (Occur::MustNot, self.primary()),
+                _ => {
+                    self.pos -= 1;
+                    (Occur::Should, self.primary())
+                }
+            };
+
+            match (occur, query) {
+                (_, None) => {}
+                (Occur::Should, Some(Query::Text(text))) => words.push(text),
+                (Occur::Should, Some(query)) => should.push(query),
+                (Occur::Must, Some(query)) => must.push(query),
+                (Occur::MustNot, Some(query)) => must_not.push(query),
+            }
+        }
+
+        if !words.is_empty() {
+            should.insert(0, Query::Text(words.join(" ")));
+        }
+        if must.is_empty() && must_not.is_empty() && should.len() == 1 {
+            return should.remove(0);
+        }
+        Query::Bool {
+            should,
+            must,
+            must_not,
+        }
+    }
+
+    /// A single clause, or `None` if the next lexeme cannot start one.
+    fn primary(&mut self) -> Option<Query> {
+        match self.next()? {
+            Lexeme::Open => {
+                let query = self.or();
+                // Tolerate a missing closing parenthesis
+                if self.peek() == Some(Lexeme::Close) {
+                    self.pos += 1;
+                }
+                Some(query)
+            }
+            Lexeme::Close => None,
+            Lexeme::Plus | Lexeme::Minus => self.primary(),
+            Lexeme::Phrase(phrase) => Some(Query::Phrase(phrase.to_string())),
+            Lexeme::Field(name) => match self.peek() {
+                Some(Lexeme::Word(value) | Lexeme::Phrase(value)) => {
+                    self.pos += 1;
+                    Some(Query::Filter {
+                        field: Field::from_name(name),
+                        pattern: value.to_string(),
+                    })
+                }
+                _ => None,
+            },
+            Lexeme::Word(word) => match (self.peek(), self.lexemes.get(self.pos + 1)) {
+                (Some(Lexeme::Word(op)), Some(Lexeme::Word(right)))
+                    if near_distance(op).is_some() =>
+                {
+                    self.pos += 2;
+                    Some(Query::Near {
+                        left: word.to_string(),
+                        right: right.to_string(),
+                        distance: near_distance(op).unwrap_or_default(),
+                    })
+                }
+                _ => match fuzzy_word(word) {
+                    Some((text, distance)) => Some(Query::Fuzzy {
+                        text: text.to_string(),
+                        distance,
+                    }),
+                    None => Some(Query::Text(word.to_string())),
+                },
+            },
+        }
+    }
+}
+
+/// Drop empty queries, keep a single remaining one as is and combine several
+/// with `combine`.
+fn simplify(queries: Vec<Query>, combine: fn(Vec<Query>) -> Query) -> Query {
+    let mut queries: Vec<Query> = queries
+        .into_iter()
+        .filter(|query| !query.is_empty())
+        .collect();
+    match queries.len() {
+        0 => Query::Bool {
+            should: Vec::new(),
+            must: Vec::new(),
+            must_not: Vec::new(),
+        },
+        1 => queries.remove(0),
+        _ => combine(queries),
+    }
+}
+
+/// Parse a query. Parsing never fails: stray parentheses and operators without
+/// operands are skipped, and a `NEAR/k` without a word on both sides is plain
+/// text.
+pub fn parse(query: &str) -> Query {
+    let mut parser = Parser {
+        lexemes: lex(query),
+        pos: 0,
+    };
+
+    let mut queries = vec![parser.or()];
+    while parser.next().is_some() {
+        // A stray closing parenthesis ends the top level early
+        queries.push(parser.or());
+    }
+
+    simplify(queries, |should| Query::Bool {
+        should,
+        must: Vec::new(),
+        must_not: Vec::new(),
+    })
+}
+
+impl Query {
+    /// Whether the query has no clauses at all.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Query::Bool {
+                should,
+                must,
+                must_not,
+            } => should.is_empty() && must.is_empty() && must_not.is_empty(),
+            Query::And(queries) | Query::Or(queries) => queries.iter().all(Query::is_empty),
+            _ => false,
+        }
+    }
+
+    /// Text of the clauses that can make a document match, leaving out
+    /// excluded clauses and field filters.
+    pub fn positive_text(&self) -> Vec<&str> {
+        match self {
+            Query::Text(text) | Query::Phrase(text) | Query::Fuzzy { text, .. } => {
+                vec![text.as_str()]
+            }
+            Query::Near { left, right, .. } => vec![left.as_str(), right.as_str()],
+            Query::Filter { .. } => Vec::new(),
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(Query::positive_text).collect()
+            }
+            Query::Bool { should, must, .. } => should
+                .iter()
+                .chain(must)
+                .flat_map(Query::positive_text)
+                .collect(),
+        }
+    }
+}
+
+/// Match `text` against a pattern where `*` stands for any run of characters
+/// and `?` for a single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, start)) => {
+                    p = star + 1;
+                    t = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,110 @@
+use crate::lexer::is_section_number;
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Prefix of the files holding a single question.
+pub const QUESTION_PREFIX: &str = "question-";
+
+/// A question of a content page, from its "1.1.x Title" header up to the
+/// next question.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// Section number, the key of the question in `tags.csv`.
+    pub section: String,
+    pub title: String,
+    /// Full text of the question including its header lines.
+    pub text: String,
+}
+
+/// A question together with the tree node of the page it was extracted from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QuestionId {
+    pub node_id: String,
+    pub section: String,
+}
+
+impl QuestionId {
+    pub fn new(node_id: &str, section: &str) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            section: section.to_string(),
+        }
+    }
+
+    /// File name of the question, `question-<node id>-<section>.txt`.
+    pub fn file_name(&self) -> String {
+        format!("{}{}-{}.txt", QUESTION_PREFIX, self.node_id, self.section)
+    }
+
+    /// Parse a path written under [`QuestionId::file_name`]. Node ids may
+    /// contain dashes, section numbers never do.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?.strip_prefix(QUESTION_PREFIX)?;
+        let (node_id, section) = stem.rsplit_once('-')?;
+        (!node_id.is_empty() && is_section_number(section)).then(|| Self::new(node_id, section))
+    }
+}
+
+/// Section number and title of a question header line such as
+/// `1.1.3 Ladung und Stromstärke`.
+fn header(line: &str) -> Option<(&str, &str)> {
+    let (number, title) = line.trim().split_once(char::is_whitespace)?;
+    is_section_number(number).then(|| (number, title.trim()))
+}
+
+/// Split the text of a content page into its questions. A question starts at
+/// a line beginning with a new section number; its question line repeats the
+/// number and stays part of it. Text before the first header is dropped.
+pub fn split_questions(text: &str) -> Vec<Question> {
+    let mut questions: Vec<Question> = Vec::new();
+
+    for line in text.lines() {
+        if let Some((section, title)) = header(line) {
+            if questions.last().is_none_or(|last| last.section != section) {
+                questions.push(Question {
+                    section: section.to_string(),
+                    title: title.to_string(),
+                    text: String::new(),
+                });
+            }
+        }
+
+        if let Some(question) = questions.last_mut() {
+            question.text.push_str(line);
+            question.text.push('\n');
+        }
+    }
+
+    questions
+}
+
+/// Split a page dump `extracted-<node id>.txt` into question files next to it
+/// and remove the dump. Returns the written files; a page without questions
+/// is left as it is.
+pub fn split_page_file(path: &Path) -> Result<Vec<PathBuf>> {
+    let node_id = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_prefix("extracted-"))
+        .with_context(|| format!("{:?} is not an extracted page", path))?;
+    let text =
+        fs::read_to_string(path).with_context(|| format!("could not read page {:?}", path))?;
+
+    let questions = split_questions(&text);
+    let mut written = Vec::new();
+    for question in &questions {
+        let question_path =
+            path.with_file_name(QuestionId::new(node_id, &question.section).file_name());
+        fs::write(&question_path, &question.text)
+            .with_context(|| format!("could not write question {:?}", question_path))?;
+        written.push(question_path);
+    }
+
+    if !written.is_empty() {
+        fs::remove_file(path)?;
+    }
+    Ok(written)
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+/// Statistics of one query term in one document and in the whole index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TermStats {
+    /// Occurrences of the term in the document.
+    pub tf: usize,
+    /// Number of terms in the document.
+    pub doc_len: usize,
+    /// Average number of terms per document in the index.
+    pub avg_doc_len: f32,
+    /// Number of documents containing the term.
+    pub df: usize,
+    /// Number of documents in the index.
+    pub doc_count: usize,
+}
+
+/// Scores a single query term against a document; a document's score for a
+/// query is the sum over its terms.
+pub trait Scorer {
+    fn score(&self, stats: &TermStats) -> f32;
+}
+
+/// The built-in ranking functions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Scoring {
+    /// Term count divided by document length, times `log10(N / df)`.
+    #[default]
+    TfIdf,
+    /// Okapi BM25 with term frequency saturation `k1` and length normalization `b`.
+    Bm25 { k1: f32, b: f32 },
+    /// `(1 + ln tf) * ln(1 + N / df)`, which dampens repeated terms and never
+    /// gives a zero weight to terms present in every document.
+    LogTfIdf,
+}
+
+impl Scoring {
+    /// BM25 with the usual parameters `k1 = 1.2` and `b = 0.75`.
+    pub fn bm25() -> Self {
+        Scoring::Bm25 { k1: 1.2, b: 0.75 }
+    }
+}
+
+impl Scorer for Scoring {
+    fn score(&self, stats: &TermStats) -> f32 {
+        if stats.tf == 0 || stats.doc_len == 0 {
+            return 0.0;
+        }
+        let tf = stats.tf as f32;
+        let n = stats.doc_count as f32;
+        let df = stats.df.max(1) as f32;
+
+        match *self {
+            Scoring::TfIdf => tf / stats.doc_len as f32 * (n / df).log10(),
+            Scoring::Bm25 { k1, b } => {
+                let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+                let norm = 1.0 - b + b * stats.doc_len as f32 / stats.avg_doc_len.max(1.0);
+                idf * tf * (k1 + 1.0) / (tf + k1 * norm)
+            }
+            Scoring::LogTfIdf => (1.0 + tf.ln()) * (1.0 + n / df).ln(),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::document::{DocId, Metadata};
+use crate::lexer::Token;
+use std::{collections::HashSet, ops::Range};
+
+/// Number of passages returned per search hit.
+pub const SNIPPET_COUNT: usize = 2;
+
+/// Longest passage in bytes before it is shortened around its first match.
+pub const SNIPPET_LEN: usize = 240;
+
+/// Marks a shortened passage.
+const ELLIPSIS: &str = "…";
+
+/// A search result with the passages that explain why it matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub id: DocId,
+    pub score: f32,
+    pub metadata: Metadata,
+    /// Best matching passages in document order; empty if the document text
+    /// is neither stored nor readable anymore.
+    pub snippets: Vec<Snippet>,
+}
+
+/// A passage of a document with the byte ranges of matched terms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    /// Sorted, non-overlapping ranges into `text`.
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// How highlighted matches are rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Highlight {
+    /// Bold yellow terminal text.
+    Ansi,
+    /// `<mark>` elements, with the rest of the text HTML-escaped.
+    Html,
+    /// Arbitrary strings around each match, e.g. `[[` and `]]`.
+    Markers { open: String, close: String },
+}
+
+impl Snippet {
+    /// The passage with its matches highlighted in `style`.
+    pub fn render(&self, style: &Highlight) -> String {
+        let (open, close) = match style {
+            Highlight::Ansi => ("\x1b[1;33m", "\x1b[0m"),
+            Highlight::Html => ("<mark>", "</mark>"),
+            Highlight::Markers { open, close } => (open.as_str(), close.as_str()),
+        };
+        let escape = |text: &str| match style {
+            Highlight::Html => escape_html(text),
+            _ => text.to_string(),
+        };
+
+        let mut rendered = String::new();
+        let mut last = 0;
+        for range in &self.highlights {
+            rendered.push_str(&escape(&self.text[last..range.start]));
+            rendered.push_str(open);
+            rendered.push_str(&escape(&self.text[range.clone()]));
+            rendered.push_str(close);
+            last = range.end;
+        }
+        rendered.push_str(&escape(&self.text[last..]));
+        rendered
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Pick up to `count` passages of `text` with the most matches of `terms`.
+///
+/// `tokens` are the analyzed tokens of `text`; a token matches if its term is
+/// in `terms`, and its span is highlighted. Passages are lines, shortened to
+/// about `max_len` bytes around their first match.
+pub fn snippets(
+    text: &str,
+    tokens: &[Token],
+    terms: &HashSet<String>,
+    count: usize,
+    max_len: usize,
+) -> Vec<Snippet> {
+    let mut matches: Vec<(Range<usize>, &str)> = tokens
+        .iter()
+        .filter(|token| terms.contains(&token.term))
+        .map(|token| (token.span.clone(), token.term.as_str()))
+        .collect();
+    matches.sort_by_key(|(span, _)| (span.start, span.end));
+
+    // Rank lines by distinct matched terms, then by number of matches
+    let mut passages = Vec::new();
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let line_range = line_start..line_start + line.len();
+        line_start = line_range.end + 1;
+
+        let in_line: Vec<&(Range<usize>, &str)> = matches
+            .iter()
+            .filter(|(span, _)| span.start >= line_range.start && span.end <= line_range.end)
+            .collect();
+        if in_line.is_empty() {
+            continue;
+        }
+        let distinct: HashSet<&str> = in_line.iter().map(|(_, term)| *term).collect();
+        let spans = merge(in_line.iter().map(|(span, _)| span.clone()));
+        passages.push(((distinct.len(), spans.len()), line_range, spans));
+    }
+    passages.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.start.cmp(&b.1.start)));
+    passages.truncate(count);
+    passages.sort_by_key(|(_, line, _)| line.start);
+
+    passages
+        .into_iter()
+        .map(|(_, line, spans)| window(text, line, &spans, max_len))
+        .collect()
+}
+
+/// Merge overlapping or touching ranges of sorted `spans`.
+fn merge(spans: impl Iterator<Item = Range<usize>>) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+    merged
+}
+
+/// Cut a snippet of at most about `max_len` bytes out of `line`, starting a
+/// little before the first highlight and at a word boundary.
+fn window(text: &str, line: Range<usize>, spans: &[Range<usize>], max_len: usize) -> Snippet {
+    let (mut start, mut end) = (line.start, line.end);
+    if end - start > max_len {
+        let first = spans.first().map_or(start, |span| span.start);
+        start = first.saturating_sub(max_len / 4).max(line.start);
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        if start > line.start {
+            // Skip the partial word in front of the window
+            if let Some(space) = text[start..first].find(char::is_whitespace) {
+                start += space + 1;
+            }
+        }
+
+        end = (start + max_len).min(line.end);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end < line.end {
+            if let Some(space) = text[start..end].rfind(char::is_whitespace) {
+                end = start + space;
+            }
+        }
+    }
+
+    let mut snippet = String::new();
+    if start > line.start {
+        snippet.push_str(ELLIPSIS);
+    }
+    let offset = snippet.len();
+    snippet.push_str(text[start..end].trim_end());
+    let body_end = snippet.len();
+    if end < line.end {
+        snippet.push_str(ELLIPSIS);
+    }
+
+    let highlights = spans
+        .iter()
+        .filter(|span| span.start >= start && span.start < end)
+        .map(|span| span.start - start + offset..(span.end.min(end) - start + offset).min(body_end))
+        .filter(|range| range.start < range.end)
+        .collect();
+
+    Snippet {
+        text: snippet,
+        highlights,
+    }
+}
@@ -1,6 +1,11 @@
 use std::time::Duration;
 
-use crate::{lexer::Lexer, tree::FileTree};
+use crate::{
+    analyzer::{Analyzer, StandardAnalyzer},
+    lexer::Language,
+    question::{split_questions, QuestionId},
+    tree::FileTree,
+};
 use anyhow::{bail, Context, Result};
 use async_recursion::async_recursion;
 use thirtyfour::{prelude::*, support, WebDriver};
@@ -146,12 +151,27 @@ impl Spider {
             }
         }
 
+        // One file per question, or the whole page if it has no question headers
         let raw_path = format!("extracted-{}.txt", id);
-        fs::write(&raw_path, &full_text)
-            .await
-            .context("failed to write tokens")?;
+        let questions = split_questions(&full_text);
+        if questions.is_empty() {
+            fs::write(&raw_path, &full_text)
+                .await
+                .context("failed to write extracted text")?;
+        } else {
+            for question in &questions {
+                let path = QuestionId::new(id, &question.section).file_name();
+                fs::write(&path, &question.text)
+                    .await
+                    .with_context(|| format!("failed to write question {}", question.section))?;
+            }
+            // A page dump from an earlier extraction would index the questions twice
+            if fs::try_exists(&raw_path).await? {
+                fs::remove_file(&raw_path).await?;
+            }
+        }
 
-        let tokens: Vec<String> = Lexer::new(&full_text).collect();
+        let tokens = StandardAnalyzer::default().terms(&full_text, Language::default());
 
         let tok_path = format!("tokens-{}.txt", id);
         let tok_data = tokens.join("\n");
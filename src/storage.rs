@@ -0,0 +1,138 @@
+use crate::model::Model;
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// First word of the header line of every index file.
+const MAGIC: &str = "tag-spider-index";
+
+/// Version of the on-disk layout, bumped on incompatible changes to `Model`.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Encoding of the model following the header line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// JSON, readable with standard tooling.
+    Json,
+    /// Compact bincode encoding.
+    Binary,
+}
+
+impl IndexFormat {
+    fn name(self) -> &'static str {
+        match self {
+            IndexFormat::Json => "json",
+            IndexFormat::Binary => "binary",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(IndexFormat::Json),
+            "binary" => Some(IndexFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// Write `model` to `path`: a header line `tag-spider-index v<N> <format>`
+/// followed by the encoded model. The file is written next to `path` first
+/// and renamed over it, so readers never observe a partial index.
+pub fn save<T: Serialize>(model: &T, path: &Path, format: IndexFormat) -> Result<()> {
+    let tmp_path = temporary_path(path);
+    let file = File::create(&tmp_path)
+        .with_context(|| format!("could not create index file {:?}", tmp_path))?;
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "{} v{} {}", MAGIC, FORMAT_VERSION, format.name())?;
+    match format {
+        IndexFormat::Json => serde_json::to_writer(&mut writer, model)?,
+        IndexFormat::Binary => bincode::serialize_into(&mut writer, model)?,
+    }
+
+    let file = writer.into_inner().context("could not flush index file")?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("could not move index into place at {:?}", path))?;
+    Ok(())
+}
+
+/// Read a model written by [`save`], detecting its format from the header.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = File::open(path).with_context(|| format!("could not open index {:?}", path))?;
+    let mut reader = BufReader::new(file);
+
+    let mut header = String::new();
+    reader.read_line(&mut header)?;
+    let format = parse_header(header.trim_end())
+        .with_context(|| format!("invalid index header in {:?}", path))?;
+
+    let mut payload = Vec::new();
+    reader.read_to_end(&mut payload)?;
+    let model = match format {
+        IndexFormat::Json => serde_json::from_slice(&payload).map_err(anyhow::Error::from),
+        IndexFormat::Binary => bincode::deserialize(&payload).map_err(anyhow::Error::from),
+    };
+    model.with_context(|| format!("could not decode {:?} index {:?}", format, path))
+}
+
+/// Check magic and version of a header line and return its format.
+fn parse_header(header: &str) -> Result<IndexFormat> {
+    let mut fields = header.split(' ');
+    if fields.next() != Some(MAGIC) {
+        bail!("not a tag spider index");
+    }
+
+    let version: u32 = fields
+        .next()
+        .and_then(|v| v.strip_prefix('v'))
+        .and_then(|v| v.parse().ok())
+        .context("missing format version")?;
+    if version > FORMAT_VERSION {
+        bail!(
+            "index format v{} is newer than the supported v{}",
+            version,
+            FORMAT_VERSION
+        );
+    }
+    if version < FORMAT_VERSION {
+        bail!(
+            "index format v{} is outdated, rebuild the index as v{}",
+            version,
+            FORMAT_VERSION
+        );
+    }
+
+    fields
+        .next()
+        .and_then(IndexFormat::from_name)
+        .context("unknown index encoding")
+}
+
+/// Sibling of `path` used while writing, e.g. `.index.json.tmp`.
+fn temporary_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.tmp", name))
+}
+
+impl<A> Model<A>
+where
+    Self: Serialize + DeserializeOwned,
+{
+    /// Load an index previously written with [`Model::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        load(path.as_ref())
+    }
+
+    /// Atomically write the index to `path` in the given format.
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: IndexFormat) -> Result<()> {
+        save(self, path.as_ref(), format)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::analyzer::Analyzer;
+use crate::document::DocId;
+use crate::model::{Model, TermVector};
+use crate::storage::{self, IndexFormat};
+use crate::tags::split_tags;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// Tagged questions consulted for a suggestion by default.
+pub const DEFAULT_NEIGHBOURS: usize = 10;
+
+/// A proposed tag with the share of similar questions carrying it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSuggestion {
+    pub tag: String,
+    /// Between 0 and 1: the similarity-weighted fraction of the nearest
+    /// tagged questions that have this tag.
+    pub confidence: f32,
+}
+
+/// A tagged question the suggester learned from.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Example {
+    id: DocId,
+    vector: TermVector,
+    tags: Vec<String>,
+}
+
+/// Suggests tags for a question from the tags of the most similar tagged
+/// questions, k-nearest neighbours over the TF-IDF vectors of a [`Model`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TagSuggester {
+    /// Number of nearest tagged questions that vote on the tags.
+    pub neighbours: usize,
+    examples: Vec<Example>,
+}
+
+impl Default for TagSuggester {
+    fn default() -> Self {
+        Self {
+            neighbours: DEFAULT_NEIGHBOURS,
+            examples: Vec::new(),
+        }
+    }
+}
+
+/// Cosine similarity of two unit length vectors.
+pub fn cosine(a: &TermVector, b: &TermVector) -> f32 {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    short
+        .iter()
+        .filter_map(|(term, weight)| Some(weight * long.get(term)?))
+        .sum()
+}
+
+impl TagSuggester {
+    /// Learn from the questions in `model` that have tags in `tags`, the
+    /// `Key,Tags` rows returned by [`crate::tags::load_csv_data`].
+    pub fn train<A: Analyzer>(model: &Model<A>, tags: &HashMap<String, String>) -> Self {
+        let mut examples: Vec<Example> = model
+            .docs
+            .keys()
+            .filter_map(|id| {
+                let DocId::Question(question) = id else {
+                    return None;
+                };
+                let tags = split_tags(tags.get(&question.section)?);
+                if tags.is_empty() {
+                    return None;
+                }
+                Some(Example {
+                    id: id.clone(),
+                    vector: model.term_vector(id)?,
+                    tags,
+                })
+            })
+            .collect();
+        examples.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Self {
+            examples,
+            ..Self::default()
+        }
+    }
+
+    /// Number of tagged questions learned from.
+    pub fn len(&self) -> usize {
+        self.examples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.examples.is_empty()
+    }
+
+    /// Up to `n` tags for a question with the TF-IDF `vector`, most confident
+    /// first.
+    pub fn suggest(&self, vector: &TermVector, n: usize) -> Vec<TagSuggestion> {
+        self.vote(vector, None, n)
+    }
+
+    /// Up to `n` tags for the indexed document `id`. The document itself is
+    /// not consulted, even if it was learned from.
+    pub fn suggest_for<A: Analyzer>(
+        &self,
+        model: &Model<A>,
+        id: &DocId,
+        n: usize,
+    ) -> Vec<TagSuggestion> {
+        match model.term_vector(id) {
+            Some(vector) => self.vote(&vector, Some(id), n),
+            None => Vec::new(),
+        }
+    }
+
+    /// Up to `n` tags for the question `text`, analyzed by `model`.
+    pub fn suggest_text<A: Analyzer>(
+        &self,
+        model: &Model<A>,
+        text: &str,
+        n: usize,
+    ) -> Vec<TagSuggestion> {
+        self.suggest(&model.text_vector(text), n)
+    }
+
+    fn vote(&self, vector: &TermVector, skip: Option<&DocId>, n: usize) -> Vec<TagSuggestion> {
+        let mut nearest: Vec<(f32, &Example)> = self
+            .examples
+            .iter()
+            .filter(|example| Some(&example.id) != skip)
+            .map(|example| (cosine(vector, &example.vector), example))
+            .filter(|(similarity, _)| *similarity > 0.0)
+            .collect();
+        nearest.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.id.cmp(&b.1.id)));
+        nearest.truncate(self.neighbours);
+
+        // Tags are compared case-insensitively and shown as first seen in
+        // the nearest question
+        let total: f32 = nearest.iter().map(|(similarity, _)| similarity).sum();
+        let mut votes: HashMap<String, (String, f32)> = HashMap::new();
+        for (similarity, example) in &nearest {
+            for tag in &example.tags {
+                votes
+                    .entry(tag.to_lowercase())
+                    .or_insert_with(|| (tag.clone(), 0.0))
+                    .1 += similarity;
+            }
+        }
+
+        let mut suggestions: Vec<TagSuggestion> = votes
+            .into_values()
+            .map(|(tag, weight)| TagSuggestion {
+                tag,
+                confidence: (weight / total).min(1.0),
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.tag.cmp(&b.tag))
+        });
+        suggestions.truncate(n);
+        suggestions
+    }
+
+    /// Load a suggester written with [`TagSuggester::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        storage::load(path.as_ref())
+    }
+
+    /// Atomically write the suggester to `path` in the given format.
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: IndexFormat) -> Result<()> {
+        storage::save(self, path.as_ref(), format)
+    }
+}
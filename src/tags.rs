@@ -0,0 +1,28 @@
+use anyhow::{Context, Result};
+use csv::Reader;
+use std::{collections::HashMap, path::Path};
+
+/// Load CSV data for tags: question key (section number) → comma separated tags.
+pub fn load_csv_data<P: AsRef<Path>>(path: P) -> Result<HashMap<String, String>> {
+    let path = path.as_ref();
+    let mut tags: HashMap<String, String> = HashMap::new();
+    let mut reader =
+        Reader::from_path(path).with_context(|| format!("could not open tags file {:?}", path))?;
+
+    for line in reader.records() {
+        let record = line?;
+        tags.insert(record[0].to_string(), record[1].to_string());
+    }
+
+    Ok(tags)
+}
+
+/// Split a tag cell such as `"Spannung, elektrische Spannung,"` into its tags.
+pub fn split_tags(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
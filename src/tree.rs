@@ -116,6 +116,27 @@ impl FileTree {
         }
     }
 
+    /// Ids of the nodes from the root down to `id`, or `None` if `id` is not
+    /// in the tree.
+    pub fn breadcrumb(&self, id: &str) -> Option<Vec<&str>> {
+        let mut trail = vec![self.nodes.get(id)?.id.as_str()];
+        let mut current = id;
+        while let Some(parent) = self
+            .nodes
+            .get(current)
+            .and_then(|node| node.parent.as_deref())
+        {
+            // A cycle would otherwise loop forever
+            if trail.contains(&parent) {
+                break;
+            }
+            trail.push(parent);
+            current = parent;
+        }
+        trail.reverse();
+        Some(trail)
+    }
+
     pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let data = fs::read_to_string(path)?;
         let mut tree: FileTree =
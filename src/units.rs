@@ -0,0 +1,119 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// SI prefixes as (symbol, German name).
+static PREFIXES: &[(&str, &str)] = &[
+    ("p", "piko"),
+    ("n", "nano"),
+    ("µ", "mikro"),
+    ("μ", "mikro"),
+    ("u", "mikro"),
+    ("m", "milli"),
+    ("k", "kilo"),
+    ("M", "mega"),
+    ("G", "giga"),
+];
+
+/// Alternative spellings of prefix names, mapped onto the German name.
+static PREFIX_ALIASES: &[(&str, &str)] = &[("pico", "piko"), ("micro", "mikro")];
+
+/// Units as (symbol, German name); the name is the canonical unit term.
+static UNITS: &[(&str, &str)] = &[
+    ("A", "ampere"),
+    ("V", "volt"),
+    ("Ω", "ohm"),
+    ("W", "watt"),
+    ("F", "farad"),
+    ("C", "coulomb"),
+    ("Hz", "hertz"),
+    ("J", "joule"),
+    ("H", "henry"),
+    ("T", "tesla"),
+    ("Wb", "weber"),
+    ("S", "siemens"),
+    ("Ah", "amperestunde"),
+    ("As", "amperesekunde"),
+    ("Wh", "wattstunde"),
+    ("VA", "voltampere"),
+    ("s", "sekunde"),
+    ("h", "stunde"),
+];
+
+/// Unit names too common as plain words to be recognized without a prefix.
+static AMBIGUOUS_NAMES: &[&str] = &["sekunde", "stunde"];
+
+/// A unit with an optional SI prefix, both by canonical German name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unit {
+    pub prefix: Option<&'static str>,
+    pub base: &'static str,
+}
+
+impl Unit {
+    /// Canonical term of the unit, e.g. `kiloohm` for both `kΩ` and `Kiloohm`.
+    pub fn name(&self) -> String {
+        format!("{}{}", self.prefix.unwrap_or_default(), self.base)
+    }
+
+    /// Parse a unit symbol such as `mA`, `kΩ` or `kAh`. Symbols are case
+    /// sensitive. With `standalone`, only symbols containing `Ω` are accepted:
+    /// without a preceding number, others are usually variables or words,
+    /// such as the `pH` of "pH-Wert" or the word "As".
+    pub fn from_symbol(symbol: &str, standalone: bool) -> Option<Self> {
+        // NFC maps the ohm sign U+2126 onto the Greek capital omega
+        let symbol: String = symbol.nfc().collect();
+        if standalone && !symbol.contains('Ω') {
+            return None;
+        }
+
+        let base = |symbol: &str| {
+            UNITS
+                .iter()
+                .find(|(sym, _)| *sym == symbol)
+                .map(|&(_, name)| name)
+        };
+
+        if let Some(name) = base(&symbol) {
+            return Some(Self {
+                prefix: None,
+                base: name,
+            });
+        }
+
+        PREFIXES.iter().find_map(|&(sym, prefix)| {
+            let rest = symbol.strip_prefix(sym)?;
+            Some(Self {
+                prefix: Some(prefix),
+                base: base(rest)?,
+            })
+        })
+    }
+
+    /// Parse a lowercased unit name such as `ampere`, `kiloohm` or
+    /// `amperestunden`. Plain `sekunde` and `stunde` are not recognized.
+    pub fn from_name(word: &str) -> Option<Self> {
+        let base = |word: &str| {
+            UNITS.iter().map(|&(_, name)| name).find(|name| {
+                word == *name || word.strip_suffix('n') == Some(name) && name.ends_with('e')
+            })
+        };
+
+        if let Some(name) = base(word) {
+            return (!AMBIGUOUS_NAMES.contains(&name)).then_some(Self {
+                prefix: None,
+                base: name,
+            });
+        }
+
+        let mut spellings = PREFIXES
+            .iter()
+            .map(|&(_, name)| (name, name))
+            .chain(PREFIX_ALIASES.iter().copied());
+        spellings.find_map(|(spelling, prefix)| {
+            let rest = word.strip_prefix(spelling)?;
+            Some(Self {
+                prefix: Some(prefix),
+                base: base(rest)?,
+            })
+        })
+    }
+}